@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+
+use crate::{get_longest_nap, get_nights_asleep, get_total_sleep, SleepPeriod};
+
+const PERIODS_HEADER: &str = "guard_id,date,start_minute,end_minute,duration";
+const SUMMARY_HEADER: &str = "guard_id,total_sleep,nights_asleep,longest_nap,sleep_periods";
+
+// One row per sleep period, ordered by guard ID, then date, then start minute.
+pub fn periods_to_csv(guard_patterns: &HashMap<usize, Vec<SleepPeriod>>) -> String {
+    let mut output = PERIODS_HEADER.to_owned();
+    for (id, period) in sorted_periods(guard_patterns) {
+        output.push_str(&format!(
+            "\n{},{},{},{},{}",
+            id,
+            period.date,
+            period.start_minute,
+            period.end_minute,
+            period.duration()
+        ));
+    }
+
+    output
+}
+
+// One row per guard with their aggregate sleep counts, ordered by guard ID.
+pub fn summary_to_csv(guard_patterns: &HashMap<usize, Vec<SleepPeriod>>) -> String {
+    let mut output = SUMMARY_HEADER.to_owned();
+    for id in sorted_ids(guard_patterns) {
+        let periods = &guard_patterns[&id];
+        output.push_str(&format!(
+            "\n{},{},{},{},{}",
+            id,
+            get_total_sleep(periods),
+            get_nights_asleep(periods),
+            get_longest_nap(periods),
+            periods.len()
+        ));
+    }
+
+    output
+}
+
+// Both the sleep periods and the per-guard summary, as a single JSON document.
+pub fn to_json(guard_patterns: &HashMap<usize, Vec<SleepPeriod>>) -> String {
+    let periods = sorted_periods(guard_patterns)
+        .iter()
+        .map(|(id, period)| {
+            format!(
+                "    {{\"guard_id\": {}, \"date\": \"{}\", \"start_minute\": {}, \"end_minute\": {}, \"duration\": {}}}",
+                id,
+                escape_json(&period.date),
+                period.start_minute,
+                period.end_minute,
+                period.duration()
+            )
+        })
+        .collect::<Vec<String>>();
+
+    let guards = sorted_ids(guard_patterns)
+        .iter()
+        .map(|id| {
+            let periods = &guard_patterns[id];
+            format!(
+                "    {{\"guard_id\": {}, \"total_sleep\": {}, \"nights_asleep\": {}, \"longest_nap\": {}, \"sleep_periods\": {}}}",
+                id,
+                get_total_sleep(periods),
+                get_nights_asleep(periods),
+                get_longest_nap(periods),
+                periods.len()
+            )
+        })
+        .collect::<Vec<String>>();
+
+    format!(
+        "{{\n  \"sleep_periods\": [\n{}\n  ],\n  \"guards\": [\n{}\n  ]\n}}",
+        periods.join(",\n"),
+        guards.join(",\n")
+    )
+}
+
+// Reads a CSV written by `periods_to_csv` back into sleep periods keyed by guard ID.
+// The duration column is optional, but must agree with the start and end minutes
+// when present.
+pub fn periods_from_csv(input: &str) -> Result<HashMap<usize, Vec<SleepPeriod>>, String> {
+    let mut guard_patterns: HashMap<usize, Vec<SleepPeriod>> = HashMap::new();
+    let mut lines = input.lines().enumerate();
+
+    let expected_header: Vec<&str> = PERIODS_HEADER.split(',').collect();
+    let header: Vec<&str> = match lines.next() {
+        Some((_, header)) => header.split(',').map(|field| field.trim()).collect(),
+        None => vec![],
+    };
+    if header != expected_header && header[..] != expected_header[..4] {
+        return Err(format!("Expected a '{}' header", PERIODS_HEADER));
+    }
+
+    for (index, line) in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let line_number = index + 1;
+        let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+        if fields.len() != 4 && fields.len() != 5 {
+            return Err(format!(
+                "Line {}: expected 4 or 5 fields, found {}",
+                line_number,
+                fields.len()
+            ));
+        }
+
+        let id = parse_field(fields[0], "guard_id", line_number)?;
+        let start = parse_field(fields[2], "start_minute", line_number)?;
+        let end = parse_field(fields[3], "end_minute", line_number)?;
+        if start > end || end > 60 {
+            return Err(format!(
+                "Line {}: invalid sleep period {}-{}",
+                line_number, start, end
+            ));
+        }
+
+        if let Some(duration) = fields.get(4) {
+            if parse_field(duration, "duration", line_number)? != end - start {
+                return Err(format!(
+                    "Line {}: duration does not match start and end minutes",
+                    line_number
+                ));
+            }
+        }
+
+        guard_patterns
+            .entry(id)
+            .or_insert(vec![])
+            .push(SleepPeriod::new(fields[1].to_owned(), start, end));
+    }
+
+    Ok(guard_patterns)
+}
+
+fn parse_field(field: &str, name: &str, line_number: usize) -> Result<usize, String> {
+    field
+        .parse::<usize>()
+        .map_err(|e| format!("Line {}: invalid {} '{}': {}", line_number, name, field, e))
+}
+
+fn sorted_ids(guard_patterns: &HashMap<usize, Vec<SleepPeriod>>) -> Vec<usize> {
+    let mut ids: Vec<usize> = guard_patterns.keys().cloned().collect();
+    ids.sort();
+    ids
+}
+
+fn sorted_periods(guard_patterns: &HashMap<usize, Vec<SleepPeriod>>) -> Vec<(usize, &SleepPeriod)> {
+    let mut periods: Vec<(usize, &SleepPeriod)> = guard_patterns
+        .iter()
+        .flat_map(|(id, periods)| periods.iter().map(move |period| (*id, period)))
+        .collect();
+    periods.sort_by(|(id1, p1), (id2, p2)| {
+        (id1, &p1.date, p1.start_minute).cmp(&(id2, &p2.date, p2.start_minute))
+    });
+    periods
+}
+
+fn escape_json(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod export_tests {
+    use super::{periods_from_csv, periods_to_csv, summary_to_csv, to_json};
+    use crate::{get_parsed_patterns, EXAMPLE};
+
+    #[test]
+    fn periods_csv() {
+        assert_eq!(
+            periods_to_csv(&get_parsed_patterns(EXAMPLE).unwrap()),
+            "guard_id,date,start_minute,end_minute,duration
+10,1518-11-01,5,25,20
+10,1518-11-01,30,55,25
+10,1518-11-03,24,29,5
+99,1518-11-02,40,50,10
+99,1518-11-04,36,46,10
+99,1518-11-05,45,55,10"
+        );
+    }
+
+    #[test]
+    fn summary_csv() {
+        assert_eq!(
+            summary_to_csv(&get_parsed_patterns(EXAMPLE).unwrap()),
+            "guard_id,total_sleep,nights_asleep,longest_nap,sleep_periods
+10,50,2,25,3
+99,30,3,10,3"
+        );
+    }
+
+    #[test]
+    fn json() {
+        let json = to_json(&get_parsed_patterns(EXAMPLE).unwrap());
+        assert!(json.contains(
+            "{\"guard_id\": 99, \"date\": \"1518-11-05\", \"start_minute\": 45, \"end_minute\": 55, \"duration\": 10}\n  ],"
+        ));
+        assert!(json.contains(
+            "{\"guard_id\": 10, \"total_sleep\": 50, \"nights_asleep\": 2, \"longest_nap\": 25, \"sleep_periods\": 3},"
+        ));
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let exported = periods_to_csv(&get_parsed_patterns(EXAMPLE).unwrap());
+        assert_eq!(
+            periods_to_csv(&periods_from_csv(&exported).unwrap()),
+            exported
+        );
+    }
+
+    #[test]
+    fn import_without_duration() {
+        let imported =
+            periods_from_csv("guard_id,date,start_minute,end_minute\n7, 1518-01-01, 3, 9\n")
+                .unwrap();
+        assert_eq!(imported[&7][0].duration(), 6);
+    }
+
+    #[test]
+    fn import_errors() {
+        assert!(periods_from_csv("").is_err());
+        assert!(periods_from_csv("id,minute\n1,2").is_err());
+
+        let header = "guard_id,date,start_minute,end_minute,duration\n";
+        for row in &[
+            "10,1518-11-01,5",
+            "x,1518-11-01,5,25,20",
+            "10,1518-11-01,25,5,20",
+            "10,1518-11-01,5,61,56",
+            "10,1518-11-01,5,25,19",
+        ] {
+            assert!(
+                periods_from_csv(&(header.to_owned() + row)).is_err(),
+                "{}",
+                row
+            );
+        }
+    }
+}
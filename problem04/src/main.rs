@@ -1,36 +1,159 @@
-#![feature(map_get_key_value)]
-
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::result::Result;
 #[macro_use]
 extern crate lazy_static;
+use crate::export::*;
 use crate::parser::*;
+use crate::report::*;
+use crate::strategy::*;
 
+mod export;
 mod parser;
+mod report;
+mod strategy;
+
+// The puzzle's example log, shared by the tests.
+#[cfg(test)]
+const EXAMPLE: &str = "[1518-11-01 00:00] Guard #10 begins shift
+[1518-11-01 00:05] falls asleep
+[1518-11-01 00:25] wakes up
+[1518-11-01 00:30] falls asleep
+[1518-11-01 00:55] wakes up
+[1518-11-01 23:58] Guard #99 begins shift
+[1518-11-02 00:40] falls asleep
+[1518-11-02 00:50] wakes up
+[1518-11-03 00:05] Guard #10 begins shift
+[1518-11-03 00:24] falls asleep
+[1518-11-03 00:29] wakes up
+[1518-11-04 00:02] Guard #99 begins shift
+[1518-11-04 00:36] falls asleep
+[1518-11-04 00:46] wakes up
+[1518-11-05 00:03] Guard #99 begins shift
+[1518-11-05 00:45] falls asleep
+[1518-11-05 00:55] wakes up";
 
 fn main() -> Result<(), String> {
-    let input = fs::read_to_string("input/data.txt").map_err(|e| e.to_string())?;
-    let guard_patterns = get_parsed_patterns(&input)?;
+    let (guard_patterns, shifts, shifts_worked) = match get_arg_value("--import")? {
+        Some(path) => {
+            let input = fs::read_to_string(path).map_err(|e| e.to_string())?;
+            (periods_from_csv(&input)?, vec![], None)
+        }
+        None => {
+            let input = fs::read_to_string("input/data.txt").map_err(|e| e.to_string())?;
+            (
+                get_parsed_patterns(&input)?,
+                get_shifts(&input)?,
+                Some(get_shift_counts(&input)?),
+            )
+        }
+    };
+
+    if let Some(dir) = get_arg_value("--export")? {
+        export_all(&dir, &guard_patterns)?;
+    }
+
+    if std::env::args().any(|arg| arg == "--report") {
+        println!("{}", render_timeline(&guard_patterns, &shifts));
+        println!("{}", render_minute_histograms(&guard_patterns));
+    }
+
+    match get_arg_value("--strategy")? {
+        Some(name) => run_strategies(&name, &guard_patterns, shifts_worked)?,
+        None => {
+            part1(&guard_patterns).map_err(|e| e.to_string())?;
+            part2(&guard_patterns).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+// Reads the value following a command line flag. Supported flags are:
+//   --strategy <name>  run the named strategy, or `all` of them
+//   --export <dir>     write the sleep periods and guard summary as CSV and JSON
+//   --import <file>    read sleep periods from a CSV instead of the raw log, which
+//                      leaves out the shift counts highest-sleep-probability needs
+fn get_arg_value(flag: &str) -> Result<Option<String>, String> {
+    let mut args = std::env::args().skip_while(|arg| arg != flag);
+    match args.next() {
+        None => Ok(None),
+        Some(_) => args
+            .next()
+            .map(Some)
+            .ok_or_else(|| format!("{} requires a value", flag)),
+    }
+}
+
+fn export_all(dir: &str, guard_patterns: &HashMap<usize, Vec<SleepPeriod>>) -> Result<(), String> {
+    let dir = std::path::Path::new(dir);
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+
+    let files = [
+        ("sleep_periods.csv", periods_to_csv(guard_patterns)),
+        ("guard_summary.csv", summary_to_csv(guard_patterns)),
+        ("sleep_log.json", to_json(guard_patterns)),
+    ];
+    for (name, contents) in files.iter() {
+        fs::write(dir.join(name), contents).map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+// Runs the named strategy, or all of them. Without `shifts_worked`, as for imported
+// data, strategies needing shift counts can't be run.
+fn run_strategies(
+    name: &str,
+    guard_patterns: &HashMap<usize, Vec<SleepPeriod>>,
+    shifts_worked: Option<HashMap<usize, usize>>,
+) -> Result<(), String> {
+    let have_shift_counts = shifts_worked.is_some();
+    let strategies: Vec<Box<dyn Strategy>> = all_strategies(shifts_worked.unwrap_or_default())
+        .into_iter()
+        .filter(|strategy| name == "all" || strategy.name() == name)
+        .collect();
+
+    if strategies.is_empty() {
+        return Err(format!("Unknown strategy '{}'", name));
+    }
+
+    for strategy in strategies {
+        if strategy.needs_shift_counts() && !have_shift_counts {
+            let message = format!(
+                "{}: not available for imported data, which has no shift counts",
+                strategy.name()
+            );
+            match name {
+                "all" => println!("{}", message),
+                _ => return Err(message),
+            }
+            continue;
+        }
+
+        match strategy.select(guard_patterns) {
+            Some(selection) => println!("{}: {}", strategy.name(), selection),
+            None => println!("{}: no guards fell asleep", strategy.name()),
+        }
+    }
 
-    part1(&guard_patterns).map_err(|e| e.to_string())?;
-    part2(&guard_patterns).map_err(|e| e.to_string())?;
     Ok(())
 }
 
 #[derive(Debug)]
 struct SleepPeriod {
+    date: String,
     start_minute: usize,
     end_minute: usize,
 }
 
 impl SleepPeriod {
-    fn new(start: usize, end: usize) -> SleepPeriod {
+    fn new(date: String, start: usize, end: usize) -> SleepPeriod {
         if start > end {
             panic!("End must be after start");
         }
 
         SleepPeriod {
+            date,
             start_minute: start,
             end_minute: end,
         }
@@ -48,10 +171,11 @@ enum State {
 }
 
 fn part1(guard_patterns: &HashMap<usize, Vec<SleepPeriod>>) -> Result<(), String> {
-    let highest_sleep_time_guard = get_highest_sleep_time_guard(&guard_patterns);
-    let most_common_minute = get_most_common_minute(guard_patterns.get(&highest_sleep_time_guard).unwrap()).0;
+    let selection = MostMinutesAsleep
+        .select(guard_patterns)
+        .ok_or("No guards fell asleep")?;
 
-    println!("Part1 (should be 131469):\nguard = {}, most common min = {}, product = {}", highest_sleep_time_guard, most_common_minute, highest_sleep_time_guard * most_common_minute);
+    println!("Part1 (should be 131469):\n{}", selection);
 
     Ok(())
 }
@@ -64,9 +188,21 @@ fn get_parsed_patterns(input: &str) -> Result<HashMap<usize, Vec<SleepPeriod>>,
     let mut current_guard: Option<usize> = None;
     let mut guard_state: State = State::Awake;
     let mut fell_asleep_min: Option<usize> = None;
+    let mut fell_asleep_date: Option<String> = None;
 
     for line in lines {
         let (data, line_type) = get_line_data(line)?;
+        let (hour, minute) = get_time(line).ok_or("Unable to parse line")?;
+        if minute >= 60 {
+            return Err(format!("Invalid minute {} in '{}'", minute, line));
+        }
+        if hour != 0 && !matches!(line_type, LineType::NewGuard) {
+            return Err(format!(
+                "Guards only sleep during the midnight hour, but found '{}'",
+                line
+            ));
+        }
+
         match line_type {
             LineType::NewGuard => {
                 if guard_state == State::Asleep {
@@ -81,6 +217,7 @@ fn get_parsed_patterns(input: &str) -> Result<HashMap<usize, Vec<SleepPeriod>>,
                 }
                 guard_state = State::Asleep;
                 fell_asleep_min = Some(data);
+                fell_asleep_date = get_date(line);
             }
             LineType::WakeUp => {
                 if guard_state == State::Awake {
@@ -89,6 +226,7 @@ fn get_parsed_patterns(input: &str) -> Result<HashMap<usize, Vec<SleepPeriod>>,
                 guard_state = State::Awake;
 
                 let new_sleep_period = SleepPeriod::new(
+                    fell_asleep_date.take().expect("Woke up without sleeping first"),
                     fell_asleep_min.expect("Woke up without sleeping first"),
                     data,
                 );
@@ -105,41 +243,104 @@ fn get_parsed_patterns(input: &str) -> Result<HashMap<usize, Vec<SleepPeriod>>,
     Ok(guard_patterns)
 }
 
-fn get_highest_sleep_time_guard(guard_sleep_periods: &HashMap<usize, Vec<SleepPeriod>>) -> usize {
-    let raw_highest = guard_sleep_periods.get_key_value(guard_sleep_periods.keys().nth(0).unwrap()).unwrap();
-    let mut highest = (raw_highest.0, get_total_sleep(&raw_highest.1));
-
-    for (id, sleep_periods) in guard_sleep_periods.iter() {
-        let current_total_sleep = get_total_sleep(&sleep_periods);
-        if current_total_sleep > highest.1 {
-            highest = (id, current_total_sleep);
+// Every shift as the date of its midnight hour and the guard working it. A guard
+// beginning their shift before midnight is working the next day's shift.
+fn get_shifts(input: &str) -> Result<Vec<(String, usize)>, String> {
+    let mut shifts = vec![];
+
+    for line in input.lines() {
+        if let (id, LineType::NewGuard) = get_line_data(line)? {
+            let date = get_date(line).ok_or("Unable to parse line")?;
+            let date = match get_time(line) {
+                Some((0, _)) => date,
+                _ => next_day(&date).ok_or_else(|| format!("Invalid date in '{}'", line))?,
+            };
+            shifts.push((date, id));
         }
     }
 
-    *highest.0
+    shifts.sort();
+    Ok(shifts)
 }
 
-fn get_total_sleep(periods: &Vec<SleepPeriod>) -> usize {
-    periods.iter().fold(0, |acc, current| acc + current.duration())
-}
+fn get_shift_counts(input: &str) -> Result<HashMap<usize, usize>, String> {
+    let mut shift_counts: HashMap<usize, usize> = HashMap::new();
+
+    for (_, id) in get_shifts(input)? {
+        *shift_counts.entry(id).or_insert(0) += 1;
+    }
 
-fn get_most_common_minute(periods: &Vec<SleepPeriod>) -> (usize, usize) {
-    let minute_occurrences = get_occurence_counts(&periods);
-    let random_key = *minute_occurrences.keys().nth(0).unwrap();
-    let random_key_value = *minute_occurrences.get(&random_key).unwrap();
+    Ok(shift_counts)
+}
 
-    let mut most_common = (random_key, random_key_value);
+// Every candidate sharing the highest count, in ascending order. Ties are broken
+// by taking the first candidate, i.e. the lowest guard ID or the earliest minute.
+#[derive(Debug, PartialEq)]
+struct Tied<T> {
+    count: usize,
+    candidates: Vec<T>,
+}
 
-    for (min, count) in minute_occurrences {
-        if count > most_common.1 {
-            most_common = (min, count);
+impl<T: Ord> Tied<T> {
+    fn from_counts<I>(counts: I) -> Option<Tied<T>>
+    where
+        I: IntoIterator<Item = (T, usize)>,
+    {
+        let mut highest: Option<Tied<T>> = None;
+
+        for (candidate, count) in counts {
+            match highest {
+                Some(ref mut tied) if count == tied.count => tied.candidates.push(candidate),
+                Some(ref tied) if count < tied.count => {}
+                _ => {
+                    highest = Some(Tied {
+                        count,
+                        candidates: vec![candidate],
+                    })
+                }
+            }
         }
+
+        highest.map(|mut tied| {
+            tied.candidates.sort();
+            tied
+        })
     }
+}
+
+fn get_highest_sleep_time_guard(guard_sleep_periods: &HashMap<usize, Vec<SleepPeriod>>) -> Option<Tied<usize>> {
+    Tied::from_counts(
+        guard_sleep_periods
+            .iter()
+            .map(|(id, sleep_periods)| (*id, get_total_sleep(sleep_periods))),
+    )
+}
+
+fn get_total_sleep(periods: &[SleepPeriod]) -> usize {
+    periods.iter().fold(0, |acc, current| acc + current.duration())
+}
+
+fn get_longest_nap(periods: &[SleepPeriod]) -> usize {
+    periods
+        .iter()
+        .map(|period| period.duration())
+        .max()
+        .unwrap_or(0)
+}
+
+fn get_nights_asleep(periods: &[SleepPeriod]) -> usize {
+    periods
+        .iter()
+        .map(|period| &period.date)
+        .collect::<HashSet<&String>>()
+        .len()
+}
 
-    most_common
+fn get_most_common_minute(periods: &[SleepPeriod]) -> Option<Tied<usize>> {
+    Tied::from_counts(get_occurence_counts(periods))
 }
 
-fn get_occurence_counts(periods: &Vec<SleepPeriod>) -> HashMap<usize, usize> {
+fn get_occurence_counts(periods: &[SleepPeriod]) -> HashMap<usize, usize> {
     let mut minute_occurrences: HashMap<usize, usize> = HashMap::new();
 
     for period in periods.iter() {
@@ -152,34 +353,137 @@ fn get_occurence_counts(periods: &Vec<SleepPeriod>) -> HashMap<usize, usize> {
     minute_occurrences
 }
 
-#[derive(Debug)]
-struct MostFrequentSleepingMinute {
-    id: usize,
-    minute: usize,
-    count: usize,
+fn part2(guard_patterns: &HashMap<usize, Vec<SleepPeriod>>) -> Result<(), String> {
+    let selection = MostFrequentMinute
+        .select(guard_patterns)
+        .ok_or("No guards fell asleep")?;
+
+    // guard = 1901, minute = 51, product = 96951
+    println!("Part2 (should be 96951):\n{}", selection);
+
+    Ok(())
 }
 
-fn part2(guard_patterns: &HashMap<usize, Vec<SleepPeriod>>) -> Result<(), String> {
-    let mut most_frequent = MostFrequentSleepingMinute {
-        id: 0,
-        minute: 0,
-        count: 0,
-    };
+#[cfg(test)]
+mod tie_breaking_tests {
+    use super::{get_highest_sleep_time_guard, get_most_common_minute, SleepPeriod, Tied};
+    use std::collections::HashMap;
+
+    fn period(start: usize, end: usize) -> SleepPeriod {
+        SleepPeriod::new("1518-11-01".to_owned(), start, end)
+    }
 
-    for (curr_id, sleep_periods) in guard_patterns.iter() {
-        let (min, curr_count) = get_most_common_minute(&sleep_periods);
-        if curr_count > most_frequent.count {
-            most_frequent = MostFrequentSleepingMinute {
-                id: *curr_id,
-                minute: min,
-                count: curr_count,
+    #[test]
+    fn single_highest_count() {
+        assert_eq!(
+            Tied::from_counts(vec![(3, 1), (1, 5), (2, 4)]),
+            Some(Tied {
+                count: 5,
+                candidates: vec![1]
+            })
+        );
+    }
+
+    #[test]
+    fn ties_are_sorted_ascending() {
+        assert_eq!(
+            Tied::from_counts(vec![(7, 2), (3, 2), (5, 1), (4, 2)]),
+            Some(Tied {
+                count: 2,
+                candidates: vec![3, 4, 7]
+            })
+        );
+        assert_eq!(Tied::<usize>::from_counts(vec![]), None);
+    }
+
+    #[test]
+    fn tied_guards_are_all_reported() {
+        let guards = [(99, 10), (10, 10), (42, 10), (7, 5)];
+
+        // Every insertion order goes into its own map, and each map has its own
+        // hash seed, so the guards are also iterated in different orders.
+        for first in 0..guards.len() {
+            for second in 0..guards.len() {
+                if second == first {
+                    continue;
+                }
+                let mut order = vec![guards[first], guards[second]];
+                order.extend(guards.iter().enumerate().filter(|(index, _)| *index != first && *index != second).map(|(_, guard)| *guard));
+
+                let mut guard_patterns = HashMap::new();
+                for (id, minutes) in &order {
+                    guard_patterns.insert(*id, vec![period(0, *minutes)]);
+                }
+                assert_eq!(
+                    get_highest_sleep_time_guard(&guard_patterns),
+                    Some(Tied {
+                        count: 10,
+                        candidates: vec![10, 42, 99]
+                    }),
+                    "{:?}",
+                    order
+                );
             }
         }
     }
 
-    println!("{:?}", most_frequent);
-    // MostFrequentSleepingMinute { id: 1901, minute: 51, count: 19 }
-    // 1901 * 51 = 96,951
-    
-    Ok(())
+    #[test]
+    fn tied_minutes_are_all_reported() {
+        let most_common = get_most_common_minute(&[period(30, 35), period(10, 12)]).unwrap();
+        assert_eq!(most_common.count, 1);
+        assert_eq!(most_common.candidates, vec![10, 11, 30, 31, 32, 33, 34]);
+    }
+}
+
+#[cfg(test)]
+mod get_parsed_patterns_tests {
+    use super::{get_parsed_patterns, get_shifts};
+
+    #[test]
+    fn out_of_range_times_are_rejected() {
+        for line in &[
+            "[1518-11-01 00:75] falls asleep",
+            "[1518-11-01 00:60] falls asleep",
+            "[1518-11-01 01:05] falls asleep",
+            "[1518-11-01 23:99] Guard #10 begins shift",
+        ] {
+            let input = "[1518-11-01 00:00] Guard #10 begins shift\n".to_owned() + line;
+            assert!(get_parsed_patterns(&input).is_err(), "{}", line);
+        }
+
+        assert!(get_parsed_patterns("[1518-11-01 23:58] Guard #10 begins shift").is_ok());
+    }
+
+    #[test]
+    fn shifts_before_midnight_count_for_the_next_day() {
+        assert_eq!(
+            get_shifts(
+                "[1518-11-01 23:58] Guard #99 begins shift
+[1518-11-01 00:00] Guard #10 begins shift"
+            )
+            .unwrap(),
+            vec![("1518-11-01".to_owned(), 10), ("1518-11-02".to_owned(), 99)]
+        );
+    }
+}
+
+#[cfg(test)]
+mod run_strategies_tests {
+    use super::{get_parsed_patterns, run_strategies};
+    use std::collections::HashMap;
+
+    #[test]
+    fn imported_data_has_no_sleep_probability() {
+        let patterns = get_parsed_patterns(
+            "[1518-11-01 00:00] Guard #10 begins shift
+[1518-11-01 00:05] falls asleep
+[1518-11-01 00:25] wakes up",
+        )
+        .unwrap();
+
+        assert!(run_strategies("highest-sleep-probability", &patterns, None).is_err());
+        assert!(run_strategies("highest-sleep-probability", &patterns, Some(HashMap::new())).is_ok());
+        assert!(run_strategies("all", &patterns, None).is_ok());
+        assert!(run_strategies("longest-nap", &patterns, None).is_ok());
+    }
 }
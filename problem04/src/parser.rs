@@ -23,13 +23,61 @@ pub fn get_line_data(line: &str) -> Result<(usize, LineType), String> {
     Err("Unable to parse line".to_owned())
 }
 
-const DATE_SECTION: &'static str =
+const DATE_SECTION: &str =
     r"^\[(?P<date>\d{4}-\d{2}-\d{2}) (?P<hour>\d{2}):(?P<minute>\d{2})\]";
 
+pub fn get_date(line: &str) -> Option<String> {
+    lazy_static! {
+        static ref PARSE_EXPR: Regex = Regex::new(DATE_SECTION).unwrap();
+    }
+
+    PARSE_EXPR.captures(line).map(|caps| caps["date"].to_owned())
+}
+
+pub fn get_time(line: &str) -> Option<(usize, usize)> {
+    lazy_static! {
+        static ref PARSE_EXPR: Regex = Regex::new(DATE_SECTION).unwrap();
+    }
+
+    PARSE_EXPR.captures(line).map(|caps| {
+        (
+            caps["hour"].parse::<usize>().unwrap(),
+            caps["minute"].parse::<usize>().unwrap(),
+        )
+    })
+}
+
+// The day after a `YYYY-MM-DD` date, for shifts that begin before midnight.
+pub fn next_day(date: &str) -> Option<String> {
+    let parts: Vec<usize> = date
+        .split('-')
+        .map(|part| part.parse::<usize>().ok())
+        .collect::<Option<Vec<usize>>>()?;
+    let (year, month, day) = match parts[..] {
+        [year, month, day] => (year, month, day),
+        _ => return None,
+    };
+
+    let leap_year = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        2 if leap_year => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+
+    let (year, month, day) = match (month, day) {
+        (12, 31) => (year + 1, 1, 1),
+        (_, day) if day >= days_in_month => (year, month + 1, 1),
+        _ => (year, month, day + 1),
+    };
+    Some(format!("{:04}-{:02}-{:02}", year, month, day))
+}
+
 fn get_guard_id(line: &str) -> Option<usize> {
     lazy_static! {
         static ref PARSE_EXPR: Regex =
-            Regex::new(&(DATE_SECTION.clone().to_owned() + r" Guard #(?P<id>\d+) begins shift$"))
+            Regex::new(&(DATE_SECTION.to_owned() + r" Guard #(?P<id>\d+) begins shift$"))
                 .unwrap();
     }
 
@@ -39,7 +87,7 @@ fn get_guard_id(line: &str) -> Option<usize> {
 fn get_fall_asleep_minute(line: &str) -> Option<usize> {
     lazy_static! {
         static ref PARSE_EXPR: Regex =
-            Regex::new(&(DATE_SECTION.clone().to_owned() + r" falls asleep$")).unwrap();
+            Regex::new(&(DATE_SECTION.to_owned() + r" falls asleep$")).unwrap();
     }
 
     PARSE_EXPR.captures(line).map(|caps| caps["minute"].parse::<usize>().unwrap())
@@ -48,12 +96,51 @@ fn get_fall_asleep_minute(line: &str) -> Option<usize> {
 fn get_wake_up_minute(line: &str) -> Option<usize> {
     lazy_static! {
         static ref PARSE_EXPR: Regex =
-            Regex::new(&(DATE_SECTION.clone().to_owned() + r" wakes up$")).unwrap();
+            Regex::new(&(DATE_SECTION.to_owned() + r" wakes up$")).unwrap();
     }
 
     PARSE_EXPR.captures(line).map(|caps| caps["minute"].parse::<usize>().unwrap())
 }
 
+#[cfg(test)]
+mod get_date_tests {
+    use super::get_date;
+
+    #[test]
+    fn has_date() {
+        assert_eq!(
+            get_date("[1518-11-01 00:05] falls asleep"),
+            Some("1518-11-01".to_owned())
+        );
+    }
+
+    #[test]
+    fn no_date() {
+        assert_eq!(get_date("Guard #10 begins shift"), None);
+    }
+}
+
+#[cfg(test)]
+mod get_time_tests {
+    use super::{get_time, next_day};
+
+    #[test]
+    fn has_time() {
+        assert_eq!(get_time("[1518-11-01 23:58] Guard #99 begins shift"), Some((23, 58)));
+        assert_eq!(get_time("Guard #10 begins shift"), None);
+    }
+
+    #[test]
+    fn next_days() {
+        assert_eq!(next_day("1518-11-01"), Some("1518-11-02".to_owned()));
+        assert_eq!(next_day("1518-11-30"), Some("1518-12-01".to_owned()));
+        assert_eq!(next_day("1518-12-31"), Some("1519-01-01".to_owned()));
+        assert_eq!(next_day("1518-02-28"), Some("1518-03-01".to_owned()));
+        assert_eq!(next_day("1520-02-28"), Some("1520-02-29".to_owned()));
+        assert_eq!(next_day("1518-11"), None);
+    }
+}
+
 #[cfg(test)]
 mod get_guard_id_tests {
     use super::get_guard_id;
@@ -0,0 +1,148 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{get_occurence_counts, SleepPeriod};
+
+const MINUTES_IN_HOUR: usize = 60;
+
+// Renders the `Date ID Minute` table from the puzzle description, one row per
+// guard shift, with `#` for minutes spent asleep and `.` for minutes awake. Shifts
+// without any sleep come from `shifts`, as date and guard ID pairs.
+pub fn render_timeline(
+    guard_patterns: &HashMap<usize, Vec<SleepPeriod>>,
+    shifts_worked: &[(String, usize)],
+) -> String {
+    let mut shifts: BTreeMap<(&str, usize), Vec<&SleepPeriod>> = BTreeMap::new();
+    for (date, id) in shifts_worked.iter() {
+        shifts.entry((date, *id)).or_default();
+    }
+    for (id, periods) in guard_patterns.iter() {
+        for period in periods.iter() {
+            shifts.entry((&period.date, *id)).or_default().push(period);
+        }
+    }
+
+    let id_width = shifts
+        .keys()
+        .map(|(_, id)| format!("#{}", id).len())
+        .max()
+        .unwrap_or(0)
+        .max("ID".len());
+    let minute_column = 5 + 2 + id_width + 2;
+
+    let mut output = format!("Date   {:<width$}  Minute\n", "ID", width = id_width);
+    output.push_str(&" ".repeat(minute_column));
+    output.push_str(
+        &(0..MINUTES_IN_HOUR)
+            .map(|min| digit(min / 10))
+            .collect::<String>(),
+    );
+    output.push('\n');
+    output.push_str(&" ".repeat(minute_column));
+    output.push_str(
+        &(0..MINUTES_IN_HOUR)
+            .map(|min| digit(min % 10))
+            .collect::<String>(),
+    );
+
+    for ((date, id), periods) in shifts.iter() {
+        let mut minutes = ['.'; MINUTES_IN_HOUR];
+        for period in periods.iter() {
+            for minute in minutes[period.start_minute..period.end_minute].iter_mut() {
+                *minute = '#';
+            }
+        }
+
+        output.push_str(&format!(
+            "\n{}  {:<width$}  {}",
+            month_day(date),
+            format!("#{}", id),
+            minutes.iter().collect::<String>(),
+            width = id_width
+        ));
+    }
+
+    output
+}
+
+// Renders a bar chart per guard of how many shifts they were asleep for each minute.
+pub fn render_minute_histograms(guard_patterns: &HashMap<usize, Vec<SleepPeriod>>) -> String {
+    let mut ids: Vec<&usize> = guard_patterns.keys().collect();
+    ids.sort();
+
+    ids.iter()
+        .map(|id| render_minute_histogram(**id, &guard_patterns[id]))
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+fn render_minute_histogram(id: usize, periods: &[SleepPeriod]) -> String {
+    let minute_occurrences = get_occurence_counts(periods);
+
+    let mut output = format!("Guard #{}", id);
+    for min in 0..MINUTES_IN_HOUR {
+        let count = minute_occurrences.get(&min).cloned().unwrap_or(0);
+        output.push_str(&format!("\n{:02} |{}", min, "#".repeat(count)));
+        if count > 0 {
+            output.push_str(&format!(" {}", count));
+        }
+    }
+
+    output
+}
+
+fn digit(value: usize) -> char {
+    std::char::from_digit(value as u32, 10).unwrap()
+}
+
+fn month_day(date: &str) -> &str {
+    date.get(5..).unwrap_or(date)
+}
+
+#[cfg(test)]
+mod render_tests {
+    use super::{render_minute_histogram, render_timeline};
+    use crate::{get_parsed_patterns, get_shifts, EXAMPLE};
+
+    #[test]
+    fn timeline_matches_puzzle() {
+        let expected = "Date   ID   Minute
+            000000000011111111112222222222333333333344444444445555555555
+            012345678901234567890123456789012345678901234567890123456789
+11-01  #10  .....####################.....#########################.....
+11-02  #99  ........................................##########..........
+11-03  #10  ........................#####...............................
+11-04  #99  ....................................##########..............
+11-05  #99  .............................................##########.....";
+
+        assert_eq!(
+            render_timeline(&get_parsed_patterns(EXAMPLE).unwrap(), &[]),
+            expected
+        );
+    }
+
+    #[test]
+    fn timeline_has_a_row_per_shift() {
+        let input = EXAMPLE.to_owned() + "\n[1518-11-05 23:59] Guard #10 begins shift";
+        let timeline = render_timeline(
+            &get_parsed_patterns(&input).unwrap(),
+            &get_shifts(&input).unwrap(),
+        );
+        let rows: Vec<&str> = timeline.lines().skip(3).collect();
+
+        assert_eq!(rows.len(), 6);
+        assert_eq!(rows[0], "11-01  #10  .....####################.....#########################.....");
+        assert_eq!(rows[5], "11-06  #10  ............................................................");
+    }
+
+    #[test]
+    fn histogram_counts_overlapping_minutes() {
+        let patterns = get_parsed_patterns(EXAMPLE).unwrap();
+        let histogram = render_minute_histogram(10, &patterns[&10]);
+        let lines: Vec<&str> = histogram.lines().collect();
+
+        assert_eq!(lines[0], "Guard #10");
+        assert_eq!(lines[1 + 4], "04 |");
+        assert_eq!(lines[1 + 5], "05 |# 1");
+        assert_eq!(lines[1 + 24], "24 |## 2");
+    }
+}
@@ -0,0 +1,342 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{
+    get_highest_sleep_time_guard, get_longest_nap, get_most_common_minute, get_nights_asleep,
+    get_occurence_counts, get_total_sleep, SleepPeriod, Tied,
+};
+
+type GuardPatterns = HashMap<usize, Vec<SleepPeriod>>;
+
+// The guard and minute picked by a strategy, along with the counts backing the choice.
+// When several guard/minute pairs score equally, the lowest guard ID and then the
+// earliest minute is picked, and the others are listed in `ties`.
+#[derive(Debug, PartialEq)]
+pub struct Selection {
+    pub id: usize,
+    pub minute: usize,
+    pub minute_count: usize,
+    pub total_sleep: usize,
+    pub nights_asleep: usize,
+    pub longest_nap: usize,
+    pub ties: Vec<(usize, usize)>,
+}
+
+impl Selection {
+    fn from_candidates(
+        mut candidates: Vec<(usize, usize)>,
+        guard_patterns: &GuardPatterns,
+    ) -> Option<Selection> {
+        candidates.sort();
+        candidates.dedup();
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let (id, minute) = candidates.remove(0);
+        let periods = &guard_patterns[&id];
+        Some(Selection {
+            id,
+            minute,
+            minute_count: get_occurence_counts(periods)
+                .get(&minute)
+                .cloned()
+                .unwrap_or(0),
+            total_sleep: get_total_sleep(periods),
+            nights_asleep: get_nights_asleep(periods),
+            longest_nap: get_longest_nap(periods),
+            ties: candidates,
+        })
+    }
+
+    pub fn product(&self) -> usize {
+        self.id * self.minute
+    }
+
+    pub fn is_ambiguous(&self) -> bool {
+        !self.ties.is_empty()
+    }
+}
+
+impl fmt::Display for Selection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "guard = {}, minute = {}, product = {} (asleep at minute {} times, {} min total, {} nights, longest nap {} min)",
+            self.id,
+            self.minute,
+            self.product(),
+            self.minute_count,
+            self.total_sleep,
+            self.nights_asleep,
+            self.longest_nap
+        )?;
+
+        if self.is_ambiguous() {
+            let ties = self
+                .ties
+                .iter()
+                .map(|(id, minute)| format!("guard {} minute {}", id, minute))
+                .collect::<Vec<String>>();
+            write!(f, "\nAMBIGUOUS: tied with {}", ties.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+pub trait Strategy {
+    fn name(&self) -> &'static str;
+    fn select(&self, guard_patterns: &GuardPatterns) -> Option<Selection>;
+
+    // Whether the strategy needs to know how many shifts each guard worked, which
+    // only the raw log records.
+    fn needs_shift_counts(&self) -> bool {
+        false
+    }
+}
+
+// Part 1: the guard with the most minutes asleep, at the minute they slept most often.
+pub struct MostMinutesAsleep;
+
+impl Strategy for MostMinutesAsleep {
+    fn name(&self) -> &'static str {
+        "most-minutes-asleep"
+    }
+
+    fn select(&self, guard_patterns: &GuardPatterns) -> Option<Selection> {
+        let guards = get_highest_sleep_time_guard(guard_patterns)?;
+        Selection::from_candidates(
+            with_most_common_minutes(guard_patterns, &guards.candidates),
+            guard_patterns,
+        )
+    }
+}
+
+// Part 2: the guard most frequently asleep on the same minute.
+pub struct MostFrequentMinute;
+
+impl Strategy for MostFrequentMinute {
+    fn name(&self) -> &'static str {
+        "most-frequent-minute"
+    }
+
+    fn select(&self, guard_patterns: &GuardPatterns) -> Option<Selection> {
+        let most_frequent = Tied::from_counts(guard_patterns.iter().flat_map(|(id, periods)| {
+            get_occurence_counts(periods)
+                .into_iter()
+                .map(move |(minute, count)| ((*id, minute), count))
+        }))?;
+        Selection::from_candidates(most_frequent.candidates, guard_patterns)
+    }
+}
+
+// The guard with the longest uninterrupted nap, at the minute they slept most often.
+pub struct LongestNap;
+
+impl Strategy for LongestNap {
+    fn name(&self) -> &'static str {
+        "longest-nap"
+    }
+
+    fn select(&self, guard_patterns: &GuardPatterns) -> Option<Selection> {
+        select_guard_by(guard_patterns, get_longest_nap)
+    }
+}
+
+// The guard who fell asleep on the most nights, at the minute they slept most often.
+pub struct MostNightsAsleep;
+
+impl Strategy for MostNightsAsleep {
+    fn name(&self) -> &'static str {
+        "most-nights-asleep"
+    }
+
+    fn select(&self, guard_patterns: &GuardPatterns) -> Option<Selection> {
+        select_guard_by(guard_patterns, get_nights_asleep)
+    }
+}
+
+// The guard and minute with the highest chance of the guard being asleep on any
+// given shift. Guards missing from `shifts_worked` fall back to the number of
+// nights they were seen asleep.
+pub struct HighestSleepProbability {
+    pub shifts_worked: HashMap<usize, usize>,
+}
+
+impl Strategy for HighestSleepProbability {
+    fn name(&self) -> &'static str {
+        "highest-sleep-probability"
+    }
+
+    fn needs_shift_counts(&self) -> bool {
+        true
+    }
+
+    fn select(&self, guard_patterns: &GuardPatterns) -> Option<Selection> {
+        // Probabilities are compared as `count / shifts` fractions by cross-multiplying,
+        // so that equal probabilities are detected as ties.
+        let mut best: Option<(usize, usize)> = None;
+        let mut candidates: Vec<(usize, usize)> = vec![];
+
+        for (id, periods) in guard_patterns.iter() {
+            let shifts = self
+                .shifts_worked
+                .get(id)
+                .cloned()
+                .unwrap_or_else(|| get_nights_asleep(periods))
+                .max(1);
+
+            for (minute, count) in get_occurence_counts(periods) {
+                let ordering = match best {
+                    None => Ordering::Greater,
+                    Some((best_count, best_shifts)) => {
+                        (count * best_shifts).cmp(&(best_count * shifts))
+                    }
+                };
+                match ordering {
+                    Ordering::Greater => {
+                        best = Some((count, shifts));
+                        candidates = vec![(*id, minute)];
+                    }
+                    Ordering::Equal => candidates.push((*id, minute)),
+                    Ordering::Less => {}
+                }
+            }
+        }
+
+        Selection::from_candidates(candidates, guard_patterns)
+    }
+}
+
+pub fn all_strategies(shifts_worked: HashMap<usize, usize>) -> Vec<Box<dyn Strategy>> {
+    vec![
+        Box::new(MostMinutesAsleep),
+        Box::new(MostFrequentMinute),
+        Box::new(LongestNap),
+        Box::new(MostNightsAsleep),
+        Box::new(HighestSleepProbability { shifts_worked }),
+    ]
+}
+
+fn select_guard_by<F>(guard_patterns: &GuardPatterns, score: F) -> Option<Selection>
+where
+    F: Fn(&[SleepPeriod]) -> usize,
+{
+    let guards = Tied::from_counts(
+        guard_patterns
+            .iter()
+            .map(|(id, periods)| (*id, score(periods))),
+    )?;
+    Selection::from_candidates(
+        with_most_common_minutes(guard_patterns, &guards.candidates),
+        guard_patterns,
+    )
+}
+
+// Pairs each of the given guards with every one of their most common minutes.
+fn with_most_common_minutes(guard_patterns: &GuardPatterns, ids: &[usize]) -> Vec<(usize, usize)> {
+    ids.iter()
+        .filter_map(|id| get_most_common_minute(&guard_patterns[id]).map(|minutes| (*id, minutes)))
+        .flat_map(|(id, minutes)| {
+            minutes
+                .candidates
+                .into_iter()
+                .map(move |minute| (id, minute))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod strategy_tests {
+    use super::*;
+    use crate::{get_parsed_patterns, get_shift_counts, EXAMPLE};
+
+    #[test]
+    fn most_minutes_asleep() {
+        let selection = MostMinutesAsleep
+            .select(&get_parsed_patterns(EXAMPLE).unwrap())
+            .unwrap();
+        assert_eq!((selection.id, selection.minute), (10, 24));
+        assert_eq!(selection.product(), 240);
+        assert_eq!(selection.total_sleep, 50);
+    }
+
+    #[test]
+    fn most_frequent_minute() {
+        let selection = MostFrequentMinute
+            .select(&get_parsed_patterns(EXAMPLE).unwrap())
+            .unwrap();
+        assert_eq!((selection.id, selection.minute), (99, 45));
+        assert_eq!(selection.minute_count, 3);
+    }
+
+    #[test]
+    fn longest_nap() {
+        let selection = LongestNap
+            .select(&get_parsed_patterns(EXAMPLE).unwrap())
+            .unwrap();
+        assert_eq!(selection.id, 10);
+        assert_eq!(selection.longest_nap, 25);
+    }
+
+    #[test]
+    fn most_nights_asleep() {
+        let selection = MostNightsAsleep
+            .select(&get_parsed_patterns(EXAMPLE).unwrap())
+            .unwrap();
+        assert_eq!(selection.id, 99);
+        assert_eq!(selection.nights_asleep, 3);
+    }
+
+    #[test]
+    fn highest_sleep_probability_uses_shifts_worked() {
+        let input = EXAMPLE.to_owned() + "\n[1518-11-06 00:00] Guard #99 begins shift";
+        let strategy = HighestSleepProbability {
+            shifts_worked: get_shift_counts(&input).unwrap(),
+        };
+        let selection = strategy
+            .select(&get_parsed_patterns(&input).unwrap())
+            .unwrap();
+
+        // Guard #10 was asleep at minute 24 on both of their shifts, while guard #99
+        // was asleep at minute 45 on three of their four.
+        assert_eq!((selection.id, selection.minute), (10, 24));
+    }
+
+    #[test]
+    fn empty_patterns_select_nothing() {
+        let patterns = HashMap::new();
+        for strategy in all_strategies(HashMap::new()) {
+            assert_eq!(strategy.select(&patterns), None);
+        }
+    }
+
+    #[test]
+    fn ties_pick_lowest_guard_then_earliest_minute() {
+        let input = "[1518-11-01 00:00] Guard #99 begins shift
+[1518-11-01 00:10] falls asleep
+[1518-11-01 00:20] wakes up
+[1518-11-02 00:00] Guard #10 begins shift
+[1518-11-02 00:30] falls asleep
+[1518-11-02 00:40] wakes up";
+        let patterns = get_parsed_patterns(input).unwrap();
+
+        for strategy in all_strategies(get_shift_counts(input).unwrap()) {
+            let selection = strategy.select(&patterns).unwrap();
+            assert_eq!((selection.id, selection.minute), (10, 30));
+            assert!(selection.is_ambiguous());
+            assert!(selection.ties.contains(&(10, 31)));
+            assert!(selection.ties.contains(&(99, 10)));
+        }
+    }
+
+    #[test]
+    fn unambiguous_selection_has_no_ties() {
+        let selection = MostMinutesAsleep
+            .select(&get_parsed_patterns(EXAMPLE).unwrap())
+            .unwrap();
+        assert!(!selection.is_ambiguous());
+    }
+}
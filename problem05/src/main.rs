@@ -1,44 +1,127 @@
 use std::fs;
 use std::result::Result;
 use rayon::prelude::*;
+use crate::reactor::Reactor;
+use crate::removal::rank_removals;
+use crate::rules::ReactionRules;
+
+mod reactor;
+mod removal;
+mod rules;
+
+// The puzzle's example polymer, shared by the tests.
+#[cfg(test)]
+const EXAMPLE: &str = "dabAcCaCBAcCcaDA";
 
 fn main() -> Result<(), String> {
     let input = fs::read_to_string("input/data.txt").map_err(|e| e.to_string())?;
+    let rules = match get_arg_value("--rules")? {
+        Some(path) => fs::read_to_string(path).map_err(|e| e.to_string())?.parse()?,
+        None => ReactionRules::default(),
+    };
+
+    let max_removed = match get_arg_value("--remove-up-to")? {
+        Some(max_removed) => max_removed.parse().map_err(|e| format!("--remove-up-to: {}", e))?,
+        None => 1,
+    };
 
-    part1(&input);
-    part2(&input);
+    let reduced = part1(&input, &rules);
+    part2(&input, &reduced, &rules, max_removed);
     Ok(())
 }
 
-fn part1(input: &str) -> () {
-    let result = perform_reactions(input);
-    println!("Result = {}\nLength = {}", result, result.len());
+// Reads the value following a command line flag. Supported flags are:
+//   --rules <file>      replace the puzzle's reaction rules with those in the file
+//   --remove-up-to <k>  rank every set of up to k unit types to remove in part 2
+fn get_arg_value(flag: &str) -> Result<Option<String>, String> {
+    let mut args = std::env::args().skip_while(|arg| arg != flag);
+    match args.next() {
+        None => Ok(None),
+        Some(_) => args
+            .next()
+            .map(Some)
+            .ok_or_else(|| format!("{} requires a value", flag)),
+    }
+}
+
+fn part1(input: &str, rules: &ReactionRules) -> String {
+    let result = if input.len() > PARALLEL_CHUNK_LEN {
+        perform_reactions_parallel(input, rules)
+    } else {
+        perform_reactions(input, rules)
+    };
+    println!("Result = {}\nLength = {}", result, result.chars().count());
+    result
 }
 
-fn part2(input: &str) -> () {
-    static ASCII_LOWER: [char; 26] = [
-        'a', 'b', 'c', 'd', 'e', 
-        'f', 'g', 'h', 'i', 'j', 
-        'k', 'l', 'm', 'n', 'o',
-        'p', 'q', 'r', 's', 't', 
-        'u', 'v', 'w', 'x', 'y', 
-        'z',
-    ];
-
-    let lengths: Vec<usize> = ASCII_LOWER.par_iter().map(|to_remove| {
-        let filtered = input.clone().chars().filter(|ch| !ch.eq_ignore_ascii_case(to_remove)).collect::<String>();
-        perform_reactions(&filtered).len()
-    }).collect();
-
-    let minimum = lengths.into_iter().fold(None, |min, curr| match min {
-        None => Some(curr),
-        Some(existing) => Some(if existing < curr { existing } else { curr })
-    });
-
-    println!("Min = {}", minimum.unwrap());
+fn part2(input: &str, reduced: &str, rules: &ReactionRules, max_removed: usize) {
+    let ranking = rank_removals(input, reduced, rules, max_removed);
+    for (rank, removal) in ranking.iter().enumerate() {
+        println!("{:>4}. {}", rank + 1, removal);
+    }
+
+    match ranking.first() {
+        Some(best) => println!("Min = {}", best.length),
+        None => println!("No unit types to remove"),
+    }
 }
 
-fn perform_reactions(input: &str) -> String {
+fn perform_reactions(input: &str, rules: &ReactionRules) -> String {
+    let mut reactor = Reactor::with_capacity(input.len(), rules);
+    reactor.extend(input.chars());
+    reactor.as_str().to_owned()
+}
+
+// Reduces chunks of the polymer in parallel, then merges neighbouring reduced chunks.
+// With confluent rules merging is associative, so rayon is free to combine the
+// chunks in any grouping. Other rules fall back to reducing sequentially.
+fn perform_reactions_parallel(input: &str, rules: &ReactionRules) -> String {
+    if !rules.is_confluent() {
+        return perform_reactions(input, rules);
+    }
+
+    perform_reactions_in_chunks(input, PARALLEL_CHUNK_LEN, rules)
+}
+
+const PARALLEL_CHUNK_LEN: usize = 64 * 1024;
+
+fn perform_reactions_in_chunks(input: &str, chunk_len: usize, rules: &ReactionRules) -> String {
+    split_into_chunks(input, chunk_len)
+        .par_iter()
+        .map(|chunk| {
+            let mut reactor = Reactor::with_capacity(chunk.len(), rules);
+            reactor.extend(chunk.chars());
+            reactor
+        })
+        .reduce(|| Reactor::new(rules), |mut left, right| {
+            left.append(right);
+            left
+        })
+        .as_str()
+        .to_owned()
+}
+
+// Splits into chunks of about `chunk_len` bytes, without splitting any unit.
+fn split_into_chunks(input: &str, chunk_len: usize) -> Vec<&str> {
+    let mut chunks = vec![];
+    let mut rest = input;
+    while !rest.is_empty() {
+        let mut end = chunk_len.max(1).min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end += 1;
+        }
+
+        let (chunk, remaining) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = remaining;
+    }
+
+    chunks
+}
+
+// The original repeated-pass reduction, kept to check the stack-based reactor against.
+#[cfg(test)]
+fn perform_reactions_by_passes(input: &str) -> String {
     let mut new_str = input.to_string();
     loop {
         let start_size = new_str.len();
@@ -47,10 +130,11 @@ fn perform_reactions(input: &str) -> String {
             break;
         }
     }
-    
+
     new_str
 }
 
+#[cfg(test)]
 fn perform_single_reaction_pass(input: &str) -> String {
     let mut new_str = "".to_string();
 
@@ -63,7 +147,7 @@ fn perform_single_reaction_pass(input: &str) -> String {
                 break;
             },
             Some(next_char) => {
-                if ch.eq_ignore_ascii_case(next_char) && casings_are_different(&ch, &next_char) {
+                if ch.eq_ignore_ascii_case(next_char) && casings_are_different(&ch, next_char) {
                     iter.next();
                 } else {
                     new_str.push(ch);
@@ -75,48 +159,123 @@ fn perform_single_reaction_pass(input: &str) -> String {
     new_str
 }
 
+#[cfg(test)]
 fn casings_are_different(ch1: &char, ch2: &char) -> bool {
     ch1.is_lowercase() && !ch2.is_lowercase() || !ch1.is_lowercase() && ch2.is_lowercase()
 }
 
 #[cfg(test)]
 mod test_perform_reactions {
-    use super::{perform_single_reaction_pass, perform_reactions};
+    use super::{
+        perform_reactions, perform_reactions_by_passes, perform_reactions_in_chunks,
+        perform_reactions_parallel, perform_single_reaction_pass, EXAMPLE,
+    };
+    use crate::rules::ReactionRules;
+
+    #[test]
+    fn puzzle_example() {
+        assert_eq!(
+            perform_reactions(EXAMPLE, &ReactionRules::default()),
+            "dabCBAcaDA"
+        );
+        assert_eq!(perform_reactions_by_passes(EXAMPLE), "dabCBAcaDA");
+    }
 
     #[test]
     fn no_reactions_single_letter() {
         assert_eq!(perform_single_reaction_pass("a"), "a");
         assert_eq!(perform_single_reaction_pass("A"), "A");
 
-        assert_eq!(perform_reactions("a"), "a");
-        assert_eq!(perform_reactions("A"), "A");
+        assert_eq!(perform_reactions("a", &ReactionRules::default()), "a");
+        assert_eq!(perform_reactions("A", &ReactionRules::default()), "A");
     }
 
     #[test]
     fn no_reactions_different_letters() {
-        assert_eq!(perform_reactions("abcABC"), "abcABC");
-        assert_eq!(perform_reactions("aabbCCDD"), "aabbCCDD");
+        assert_eq!(perform_reactions("abcABC", &ReactionRules::default()), "abcABC");
+        assert_eq!(perform_reactions("aabbCCDD", &ReactionRules::default()), "aabbCCDD");
     }
 
     #[test]
     fn single_reacting_pair() {
-        assert_eq!(perform_reactions("Aa"), "");
-        assert_eq!(perform_reactions("aA"), "");
+        assert_eq!(perform_reactions("Aa", &ReactionRules::default()), "");
+        assert_eq!(perform_reactions("aA", &ReactionRules::default()), "");
     }
 
     #[test]
     fn two_reacting_pairs() {
-        assert_eq!(perform_reactions("AabB"), "");
-        assert_eq!(perform_reactions("AaCbB"), "C");
+        assert_eq!(perform_reactions("AabB", &ReactionRules::default()), "");
+        assert_eq!(perform_reactions("AaCbB", &ReactionRules::default()), "C");
     }
 
     #[test]
     fn pair_after_removal() {
-        assert_eq!(perform_reactions("ACca"), "");
+        assert_eq!(perform_reactions("ACca", &ReactionRules::default()), "");
     }
 
     #[test]
     fn pair_after_multi_removal() {
-        assert_eq!(perform_reactions("ZbACcaBz"), "");
+        assert_eq!(perform_reactions("ZbACcaBz", &ReactionRules::default()), "");
+    }
+
+    // A small linear congruential generator, so that the polymers are reproducible.
+    fn generate_polymer(seed: &mut u32, len: usize) -> String {
+        (0..len)
+            .map(|_| {
+                *seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                let unit = (b'a' + ((*seed >> 16) % 3) as u8) as char;
+                if (*seed >> 20) & 1 == 0 { unit } else { unit.to_ascii_uppercase() }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_repeated_passes() {
+        let mut seed: u32 = 12345;
+        for _ in 0..200 {
+            let polymer = generate_polymer(&mut seed, 64);
+            assert_eq!(perform_reactions(&polymer, &ReactionRules::default()), perform_reactions_by_passes(&polymer), "{}", polymer);
+        }
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        let mut seed: u32 = 54321;
+        for _ in 0..50 {
+            let polymer = generate_polymer(&mut seed, 1000);
+            let expected = perform_reactions(&polymer, &ReactionRules::default());
+            for chunk_len in &[1, 2, 7, 64, 1000] {
+                assert_eq!(perform_reactions_in_chunks(&polymer, *chunk_len, &ReactionRules::default()), expected);
+            }
+        }
+
+        assert_eq!(perform_reactions_parallel("", &ReactionRules::default()), "");
+        assert_eq!(perform_reactions_parallel("ZbACcaBz", &ReactionRules::default()), "");
+    }
+
+    #[test]
+    fn parallel_handles_multi_megabyte_polymers() {
+        let mut seed: u32 = 99;
+        let polymer = generate_polymer(&mut seed, 4 * 1024 * 1024);
+        assert_eq!(perform_reactions_parallel(&polymer, &ReactionRules::default()), perform_reactions(&polymer, &ReactionRules::default()));
+    }
+
+    #[test]
+    fn custom_rules() {
+        let rules: ReactionRules = "α <-> Α\n( -> )\n".parse().unwrap();
+        assert_eq!(perform_reactions("(αΑ)aA", &rules), "aA");
+        assert_eq!(perform_reactions(")(", &rules), ")(");
+    }
+
+    #[test]
+    fn parallel_keeps_multi_byte_units_whole() {
+        let rules: ReactionRules = "ascii-case\nα <-> Α".parse().unwrap();
+        let polymer = "xαβaΑAΑαγ".repeat(50);
+        for chunk_len in &[1, 2, 3, 5] {
+            assert_eq!(
+                perform_reactions_in_chunks(&polymer, *chunk_len, &rules),
+                perform_reactions(&polymer, &rules)
+            );
+        }
     }
 }
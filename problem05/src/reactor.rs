@@ -0,0 +1,118 @@
+use crate::rules::ReactionRules;
+
+// Reduces a polymer in a single pass by keeping the already-reduced units on a
+// stack. Each new unit either annihilates the unit on top of the stack or is
+// pushed onto it, so reducing a polymer of n units takes O(n) time.
+#[derive(Debug)]
+pub struct Reactor<'a> {
+    units: String,
+    len: usize,
+    rules: &'a ReactionRules,
+}
+
+impl<'a> Reactor<'a> {
+    pub fn new(rules: &'a ReactionRules) -> Reactor<'a> {
+        Reactor::with_capacity(0, rules)
+    }
+
+    pub fn with_capacity(capacity: usize, rules: &'a ReactionRules) -> Reactor<'a> {
+        Reactor {
+            units: String::with_capacity(capacity),
+            len: 0,
+            rules,
+        }
+    }
+
+    pub fn push(&mut self, unit: char) {
+        match self.units.chars().next_back() {
+            Some(top) if self.rules.react(top, unit) => {
+                self.units.pop();
+                self.len -= 1;
+            }
+            _ => {
+                self.units.push(unit);
+                self.len += 1;
+            }
+        }
+    }
+
+    // Appends an already-reduced polymer. With confluent rules, two reduced polymers
+    // can only react at the seam between them, so this cancels units there and
+    // copies the rest.
+    pub fn append(&mut self, other: Reactor) {
+        let mut rest = other.units.chars();
+        let mut rest_len = other.len;
+        while let (Some(top), Some(first)) = (self.units.chars().next_back(), rest.clone().next()) {
+            if !self.rules.react(top, first) {
+                break;
+            }
+            self.units.pop();
+            self.len -= 1;
+            rest.next();
+            rest_len -= 1;
+        }
+        self.units.push_str(rest.as_str());
+        self.len += rest_len;
+    }
+
+    // The number of units left, which is not necessarily the length of `as_str` in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.units
+    }
+}
+
+impl<'a> Extend<char> for Reactor<'a> {
+    fn extend<I: IntoIterator<Item = char>>(&mut self, units: I) {
+        for unit in units {
+            self.push(unit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_reactor {
+    use super::Reactor;
+    use crate::rules::ReactionRules;
+
+    #[test]
+    fn push_reacts_with_top_of_stack() {
+        let rules = ReactionRules::default();
+        let mut reactor = Reactor::new(&rules);
+        reactor.push('a');
+        reactor.push('b');
+        assert_eq!(reactor.as_str(), "ab");
+        assert_eq!(reactor.len(), 2);
+
+        reactor.push('B');
+        assert_eq!(reactor.as_str(), "a");
+
+        reactor.push('A');
+        assert_eq!(reactor.len(), 0);
+    }
+
+    #[test]
+    fn only_letters_react() {
+        let rules = ReactionRules::default();
+        let mut reactor = Reactor::new(&rules);
+        reactor.extend("1!\n\né".chars());
+        assert_eq!(reactor.as_str(), "1!\n\né");
+        assert_eq!(reactor.len(), 5);
+    }
+
+    #[test]
+    fn append_cancels_at_seam() {
+        let rules = ReactionRules::default();
+        let mut left = Reactor::new(&rules);
+        left.extend("xabc".chars());
+        let mut right = Reactor::new(&rules);
+        right.extend("CBAy".chars());
+
+        left.append(right);
+        assert_eq!(left.as_str(), "xy");
+        assert_eq!(left.len(), 2);
+    }
+}
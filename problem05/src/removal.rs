@@ -0,0 +1,187 @@
+use std::fmt;
+
+use rayon::prelude::*;
+
+use crate::reactor::Reactor;
+use crate::rules::ReactionRules;
+
+// A set of unit types removed from the polymer and the length it then reduces to.
+#[derive(Debug, PartialEq)]
+pub struct Removal {
+    pub unit_types: Vec<Vec<char>>,
+    pub length: usize,
+}
+
+impl fmt::Display for Removal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unit_types = self
+            .unit_types
+            .iter()
+            .map(|units| units.iter().collect::<String>())
+            .collect::<Vec<String>>();
+        write!(f, "{:<24} {}", unit_types.join(" "), self.length)
+    }
+}
+
+// Ranks every set of between 1 and `max_removed` unit types by the length of the
+// polymer left after removing them, shortest first. Ties are ordered by the unit
+// types removed.
+//
+// Sets are searched depth first, so with confluent rules each set starts from the
+// reduced polymer of the set it extends, rather than from the full input. Sets
+// starting with different unit types are searched in parallel.
+pub fn rank_removals(
+    input: &str,
+    reduced: &str,
+    rules: &ReactionRules,
+    max_removed: usize,
+) -> Vec<Removal> {
+    let search = Search {
+        unit_types: rules.unit_types(input),
+        input,
+        rules,
+        confluent: rules.is_confluent(),
+        max_removed,
+    };
+    let start = if search.confluent { reduced } else { input };
+
+    let mut removals: Vec<Removal> = if max_removed == 0 {
+        vec![]
+    } else {
+        (0..search.unit_types.len())
+            .into_par_iter()
+            .flat_map(|first| {
+                let mut removals = vec![];
+                search.extend(&[first], start, &mut removals);
+                removals
+            })
+            .collect()
+    };
+
+    removals.sort_by(|removal1, removal2| {
+        (removal1.length, &removal1.unit_types).cmp(&(removal2.length, &removal2.unit_types))
+    });
+    removals
+}
+
+struct Search<'a> {
+    unit_types: Vec<Vec<char>>,
+    input: &'a str,
+    rules: &'a ReactionRules,
+    confluent: bool,
+    max_removed: usize,
+}
+
+impl<'a> Search<'a> {
+    // Records the removal of the `chosen` unit types, given the reduced polymer of
+    // all but the last of them, then tries adding each later unit type in turn.
+    fn extend(&self, chosen: &[usize], parent: &str, removals: &mut Vec<Removal>) {
+        let (polymer, length) = if self.confluent {
+            self.reduce_without(parent, &chosen[chosen.len() - 1..])
+        } else {
+            self.reduce_without(self.input, chosen)
+        };
+
+        removals.push(Removal {
+            unit_types: chosen
+                .iter()
+                .map(|index| self.unit_types[*index].clone())
+                .collect(),
+            length,
+        });
+
+        if chosen.len() < self.max_removed {
+            for next in chosen[chosen.len() - 1] + 1..self.unit_types.len() {
+                let mut extended = chosen.to_vec();
+                extended.push(next);
+                self.extend(&extended, &polymer, removals);
+            }
+        }
+    }
+
+    // The reduced polymer left after removing the given unit types, and its length.
+    fn reduce_without(&self, polymer: &str, to_remove: &[usize]) -> (String, usize) {
+        let mut reactor = Reactor::with_capacity(polymer.len(), self.rules);
+        reactor.extend(polymer.chars().filter(|unit| {
+            !to_remove
+                .iter()
+                .any(|index| self.unit_types[*index].contains(unit))
+        }));
+        (reactor.as_str().to_owned(), reactor.len())
+    }
+}
+
+#[cfg(test)]
+mod test_rank_removals {
+    use super::{rank_removals, Removal};
+    use crate::{perform_reactions, EXAMPLE};
+    use crate::rules::ReactionRules;
+
+    fn rank(input: &str, rules: &ReactionRules, max_removed: usize) -> Vec<Removal> {
+        rank_removals(input, &perform_reactions(input, rules), rules, max_removed)
+    }
+
+    #[test]
+    fn single_unit_types() {
+        let rules = ReactionRules::default();
+        let ranking: Vec<(String, usize)> = rank(EXAMPLE, &rules, 1)
+            .into_iter()
+            .map(|removal| (removal.unit_types[0].iter().collect(), removal.length))
+            .collect();
+
+        assert_eq!(
+            ranking,
+            vec![
+                ("Cc".to_owned(), 4),
+                ("Aa".to_owned(), 6),
+                ("Dd".to_owned(), 6),
+                ("Bb".to_owned(), 8),
+            ]
+        );
+    }
+
+    #[test]
+    fn ranks_every_set_up_to_the_limit() {
+        let rules = ReactionRules::default();
+        assert_eq!(rank(EXAMPLE, &rules, 0).len(), 0);
+        assert_eq!(rank(EXAMPLE, &rules, 1).len(), 4);
+        assert_eq!(rank(EXAMPLE, &rules, 2).len(), 4 + 4 * 3 / 2);
+
+        let best = &rank(EXAMPLE, &rules, 2)[0];
+        assert_eq!(best.length, 0);
+        assert_eq!(best.unit_types, vec![vec!['A', 'a'], vec!['B', 'b']]);
+    }
+
+    #[test]
+    fn reused_polymers_match_reducing_from_scratch() {
+        let rules = ReactionRules::default();
+        for removal in rank(EXAMPLE, &rules, 3) {
+            let filtered: String = EXAMPLE
+                .chars()
+                .filter(|unit| !removal.unit_types.iter().any(|units| units.contains(unit)))
+                .collect();
+            assert_eq!(perform_reactions(&filtered, &rules).len(), removal.length);
+        }
+    }
+
+    #[test]
+    fn non_confluent_rules() {
+        let rules: ReactionRules = "a -> b\nb -> c\nx <-> y".parse().unwrap();
+        let ranking = rank("abcxyabc", &rules, 2);
+        assert_eq!(ranking.len(), 3);
+        assert_eq!(ranking[0].length, 0);
+        assert_eq!(ranking[0].unit_types, vec![vec!['a', 'b', 'c']]);
+        assert_eq!(ranking[2].length, 2);
+    }
+
+    #[test]
+    fn units_that_never_react() {
+        let rules: ReactionRules = "( -> )".parse().unwrap();
+        let ranking = rank("(x)((", &rules, 1);
+        let without_x = ranking
+            .iter()
+            .find(|removal| removal.unit_types == vec![vec!['x']])
+            .unwrap();
+        assert_eq!(without_x.length, 2);
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::str::FromStr;
+
+// The pairs of units that annihilate when the first is immediately followed by the
+// second. The puzzle's rule, where a letter reacts with the same letter in the
+// opposite case, is the default.
+//
+// Rules files have one rule per line. A word starting with `#` always starts a
+// comment that runs to the end of the line. A unit can be escaped with `\`, which
+// is how `#` itself is written as a unit:
+//   ascii-case     every ASCII letter reacts with itself in the opposite case
+//   α <-> Α        the two units react in either order
+//   ( -> )         `(` followed by `)` reacts, but `)` followed by `(` does not
+//   \# -> \\       `#` followed by `\` reacts
+#[derive(Debug, PartialEq)]
+pub struct ReactionRules {
+    ascii_case: bool,
+    pairs: HashSet<(char, char)>,
+}
+
+impl Default for ReactionRules {
+    fn default() -> ReactionRules {
+        ReactionRules {
+            ascii_case: true,
+            pairs: HashSet::new(),
+        }
+    }
+}
+
+impl FromStr for ReactionRules {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<ReactionRules, String> {
+        let mut rules = ReactionRules {
+            ascii_case: false,
+            pairs: HashSet::new(),
+        };
+
+        for (index, line) in input.lines().enumerate() {
+            let tokens: Vec<&str> = line
+                .split_whitespace()
+                .take_while(|token| !token.starts_with('#'))
+                .collect();
+
+            match tokens[..] {
+                [] => {}
+                ["ascii-case"] => rules.ascii_case = true,
+                [first, arrow, second] if arrow == "->" || arrow == "<->" => {
+                    let (first, second) = (parse_unit(first, index)?, parse_unit(second, index)?);
+                    rules.pairs.insert((first, second));
+                    if arrow == "<->" {
+                        rules.pairs.insert((second, first));
+                    }
+                }
+                _ => {
+                    return Err(format!(
+                        "Line {}: invalid rule '{}'",
+                        index + 1,
+                        line.trim()
+                    ))
+                }
+            }
+        }
+
+        Ok(rules)
+    }
+}
+
+impl ReactionRules {
+    pub fn react(&self, first: char, second: char) -> bool {
+        (self.ascii_case
+            && first != second
+            && first.is_ascii_alphabetic()
+            && first.eq_ignore_ascii_case(&second))
+            || self.pairs.contains(&(first, second))
+    }
+
+    // Reacting pairs are removed from a polymer in the order they appear, so with
+    // overlapping rules like `a -> b` and `b -> c`, "abc" reduces to "c" even though
+    // it could also reduce to "a". Rules are confluent when no such overlap exists,
+    // i.e. whenever `x -> y` and `y -> z` are both rules, `x == z`. Only then does
+    // the result not depend on the order of reactions, which is what lets polymers
+    // be reduced in independent chunks, or reduced before removing a unit type.
+    pub fn is_confluent(&self) -> bool {
+        let pairs = self.all_pairs();
+        let mut followers: HashMap<char, Vec<char>> = HashMap::new();
+        for (first, second) in pairs.iter() {
+            followers.entry(*first).or_default().push(*second);
+        }
+
+        pairs.iter().all(|(first, second)| {
+            followers
+                .get(second)
+                .into_iter()
+                .flatten()
+                .all(|third| third == first)
+        })
+    }
+
+    // The groups of units that react with each other, out of those with a unit in
+    // the polymer. Part 2 removes a whole group at a time, e.g. both `a` and `A`
+    // for the default rules. Units that never react are groups of their own, as
+    // removing them can still let their neighbours react.
+    pub fn unit_types(&self, polymer: &str) -> Vec<Vec<char>> {
+        let present: BTreeSet<char> = polymer.chars().collect();
+        let mut groups: BTreeMap<char, char> = BTreeMap::new();
+        for unit in present.iter() {
+            find_root(&mut groups, *unit);
+        }
+        for (first, second) in self.all_pairs() {
+            let (root1, root2) = (
+                find_root(&mut groups, first),
+                find_root(&mut groups, second),
+            );
+            if root1 != root2 {
+                groups.insert(root1.max(root2), root1.min(root2));
+            }
+        }
+
+        let mut unit_types: BTreeMap<char, BTreeSet<char>> = BTreeMap::new();
+        let units: Vec<char> = groups.keys().cloned().collect();
+        for unit in units {
+            let root = find_root(&mut groups, unit);
+            unit_types.entry(root).or_default().insert(unit);
+        }
+
+        unit_types
+            .into_values()
+            .filter(|units| !units.is_disjoint(&present))
+            .map(|units| units.into_iter().collect())
+            .collect()
+    }
+
+    // The explicit pairs, plus every opposite-case letter pair if `ascii-case` is set.
+    fn all_pairs(&self) -> Vec<(char, char)> {
+        let mut pairs: Vec<(char, char)> = self.pairs.iter().cloned().collect();
+        if self.ascii_case {
+            for unit in b'a'..=b'z' {
+                let (lower, upper) = (unit as char, unit.to_ascii_uppercase() as char);
+                pairs.push((lower, upper));
+                pairs.push((upper, lower));
+            }
+        }
+        pairs
+    }
+}
+
+fn find_root(groups: &mut BTreeMap<char, char>, unit: char) -> char {
+    let parent = *groups.entry(unit).or_insert(unit);
+    if parent == unit {
+        return unit;
+    }
+
+    let root = find_root(groups, parent);
+    groups.insert(unit, root);
+    root
+}
+
+fn parse_unit(token: &str, index: usize) -> Result<char, String> {
+    let mut chars = token
+        .strip_prefix('\\')
+        .filter(|unit| !unit.is_empty())
+        .unwrap_or(token)
+        .chars();
+    match (chars.next(), chars.next()) {
+        (Some(unit), None) => Ok(unit),
+        _ => Err(format!(
+            "Line {}: '{}' is not a single unit",
+            index + 1,
+            token
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test_reaction_rules {
+    use super::ReactionRules;
+    use crate::EXAMPLE;
+
+    #[test]
+    fn default_rules_react_opposite_cases() {
+        let rules = ReactionRules::default();
+        assert!(rules.react('a', 'A'));
+        assert!(rules.react('A', 'a'));
+        assert!(!rules.react('a', 'a'));
+        assert!(!rules.react('a', 'B'));
+        assert!(!rules.react('é', 'É'));
+    }
+
+    #[test]
+    fn parse_rules_file() {
+        let rules: ReactionRules = "# brackets only close in order
+( -> )
+
+α <-> Α  # greek
+"
+        .parse()
+        .unwrap();
+
+        assert!(rules.react('(', ')'));
+        assert!(!rules.react(')', '('));
+        assert!(rules.react('α', 'Α'));
+        assert!(rules.react('Α', 'α'));
+        assert!(!rules.react('a', 'A'));
+    }
+
+    #[test]
+    fn hash_units() {
+        let rules: ReactionRules = "# a comment
+# -> a # a commented out rule
+#b -> c
+\\# -> a # an escaped unit
+x <-> \\#
+\\\\ -> \\
+\\ -> y"
+            .parse()
+            .unwrap();
+        assert!(rules.react('#', 'a'));
+        assert!(!rules.react('a', '#'));
+        assert!(rules.react('x', '#'));
+        assert!(rules.react('#', 'x'));
+        assert!(!rules.react('b', 'c'));
+        assert!(rules.react('\\', '\\'));
+        assert!(rules.react('\\', 'y'));
+
+        let commented: ReactionRules = "ascii-case\n# -> a # note".parse().unwrap();
+        assert_eq!(commented, ReactionRules::default());
+        assert!("x <-> #".parse::<ReactionRules>().is_err());
+        assert!("x <-> y#".parse::<ReactionRules>().is_err());
+        assert!("x <-> y z".parse::<ReactionRules>().is_err());
+    }
+
+    #[test]
+    fn invalid_rules() {
+        assert!("a <-> bc".parse::<ReactionRules>().is_err());
+        assert!("a => b".parse::<ReactionRules>().is_err());
+        assert!("a".parse::<ReactionRules>().is_err());
+    }
+
+    #[test]
+    fn unit_types_group_reacting_units() {
+        let alphabet: String = ('a'..='z').collect();
+        assert_eq!(ReactionRules::default().unit_types(&alphabet).len(), 26);
+        assert_eq!(
+            ReactionRules::default().unit_types(EXAMPLE),
+            vec![
+                vec!['A', 'a'],
+                vec!['B', 'b'],
+                vec!['C', 'c'],
+                vec!['D', 'd']
+            ]
+        );
+
+        let rules: ReactionRules = "a -> b\nb -> c\nx <-> y".parse().unwrap();
+        assert_eq!(
+            rules.unit_types("cxz"),
+            vec![vec!['a', 'b', 'c'], vec!['x', 'y'], vec!['z']]
+        );
+    }
+
+    #[test]
+    fn confluence() {
+        assert!(ReactionRules::default().is_confluent());
+        assert!("ascii-case\nα <-> Α\n( -> )"
+            .parse::<ReactionRules>()
+            .unwrap()
+            .is_confluent());
+        assert!(!"a -> b\nb -> c"
+            .parse::<ReactionRules>()
+            .unwrap()
+            .is_confluent());
+        assert!(!"ascii-case\nx -> a"
+            .parse::<ReactionRules>()
+            .unwrap()
+            .is_confluent());
+    }
+}
@@ -1,38 +1,53 @@
+use crate::metric::Metric;
 use std::cmp;
-use std::collections::HashMap;
-use std::num::ParseIntError;
 use std::result::Result;
 use std::str::FromStr;
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-pub struct Point {
-    pub x: i32,
-    pub y: i32,
+pub struct Point<const N: usize = 2> {
+    pub coords: [i32; N],
 }
 
-pub type InputNode = Point;
+pub type InputNode<const N: usize = 2> = Point<N>;
 
-pub type NodeList = Vec<InputNode>;
+pub type NodeList<const N: usize = 2> = Vec<InputNode<N>>;
 
 impl Point {
     pub fn new(x: i32, y: i32) -> Self {
-        Point { x, y }
+        Point { coords: [x, y] }
+    }
+
+    pub fn x(&self) -> i32 {
+        self.coords[0]
+    }
+
+    pub fn y(&self) -> i32 {
+        self.coords[1]
     }
 }
 
-impl FromStr for Point {
-    type Err = ParseIntError;
+// Coordinates are separated by commas, with any whitespace around them.
+impl<const N: usize> FromStr for Point<N> {
+    type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let coords: Vec<&str> = s.split(", ").collect();
+        let fields: Vec<&str> = s.split(',').map(|field| field.trim()).collect();
+        if fields.len() != N {
+            return Err(format!(
+                "expected {} coordinates, found {}",
+                N,
+                fields.len()
+            ));
+        }
 
-        let x_fromstr = coords[0].parse::<i32>()?;
-        let y_fromstr = coords[1].parse::<i32>()?;
+        let mut coords = [0; N];
+        for (coord, field) in coords.iter_mut().zip(fields) {
+            *coord = field
+                .parse::<i32>()
+                .map_err(|e| format!("invalid coordinate '{}': {}", field, e))?;
+        }
 
-        Ok(Point {
-            x: x_fromstr,
-            y: y_fromstr,
-        })
+        Ok(Point { coords })
     }
 }
 
@@ -79,13 +94,13 @@ impl Area {
         let mut max_y = None;
 
         for current_point in node_list.iter() {
-            min_x = Some(min_x.map_or(current_point.x, |x| cmp::min(current_point.x, x)));
+            min_x = Some(min_x.map_or(current_point.x(), |x| cmp::min(current_point.x(), x)));
 
-            min_y = Some(min_y.map_or(current_point.y, |y| cmp::min(current_point.y, y)));
+            min_y = Some(min_y.map_or(current_point.y(), |y| cmp::min(current_point.y(), y)));
 
-            max_x = Some(max_x.map_or(current_point.x, |x| cmp::max(current_point.x, x)));
+            max_x = Some(max_x.map_or(current_point.x(), |x| cmp::max(current_point.x(), x)));
 
-            max_y = Some(max_y.map_or(current_point.y, |y| cmp::max(current_point.y, y)));
+            max_y = Some(max_y.map_or(current_point.y(), |y| cmp::max(current_point.y(), y)));
         }
 
         Area::new(
@@ -97,93 +112,198 @@ impl Area {
     }
 }
 
-#[derive(Debug)]
+// The smallest and largest coordinates of the nodes along each axis.
+pub fn bounding_box<const N: usize>(node_list: &NodeList<N>) -> Option<([i32; N], [i32; N])> {
+    let first = node_list.first()?;
+    let (mut min, mut max) = (first.coords, first.coords);
+    for node in node_list.iter() {
+        for axis in 0..N {
+            min[axis] = cmp::min(min[axis], node.coords[axis]);
+            max[axis] = cmp::max(max[axis], node.coords[axis]);
+        }
+    }
+
+    Some((min, max))
+}
+
+// Moves `line_start` to the start of the next line along the first axis within
+// the box, stepping the other axes like an odometer. Returns false, leaving it back
+// at the first line, once every line has been visited.
+pub fn next_line<const N: usize>(
+    line_start: &mut Point<N>,
+    min: &[i32; N],
+    max: &[i32; N],
+) -> bool {
+    for axis in 1..N {
+        if line_start.coords[axis] < max[axis] {
+            line_start.coords[axis] += 1;
+            return true;
+        }
+        line_start.coords[axis] = min[axis];
+    }
+
+    false
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Closest {
+    // The index in the node list of the only node at the shortest distance.
+    Sole(usize),
+    Tied,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct GridPoint {
-    is_exterior: bool,
-    distances: HashMap<InputNode, i32>,
-    sole_closest_node: Option<InputNode>,
+    distance: i64,
+    closest: Closest,
 }
 
 impl GridPoint {
-    pub fn new(is_exterior: bool, distances: HashMap<InputNode, i32>) -> Self {
+    // A point that no node has been offered for yet.
+    pub fn unreached() -> Self {
         GridPoint {
-            is_exterior,
-            distances,
-            sole_closest_node: None,
+            distance: i64::MAX,
+            closest: Closest::Tied,
         }
     }
 
-    pub fn is_exterior(&self) -> bool {
-        self.is_exterior
+    pub fn distance(&self) -> i64 {
+        self.distance
     }
 
-    pub fn insert_distance(&mut self, node: InputNode, distance: i32) -> Option<i32> {
-        self.distances.insert(node, distance)
+    pub fn closest(&self) -> Closest {
+        self.closest
     }
 
-    pub fn get_sole_closest_node(&self) -> Option<InputNode> {
-        let mut sorted = self.distances.iter().collect::<Vec<(&InputNode, &i32)>>();
-        sorted.sort_by(|a, b| a.1.cmp(&b.1));
-
-        let shortest_distance: i32 = *sorted[0].1;
-
-        let closest = sorted
-            .into_iter()
-            .take_while(|(_, distance)| **distance == shortest_distance)
-            .collect::<Vec<(&InputNode, &i32)>>();
-
-        match closest.len() {
-            0 => panic!("Unable to find closest node"),
-            1 => Some(*closest[0].0),
-            _ => None,
+    // Records a node (or a tie between nodes) at the given distance, keeping
+    // whichever is closer and marking the point as tied if they are equally close.
+    pub fn offer(&mut self, distance: i64, closest: Closest) {
+        if distance < self.distance {
+            self.distance = distance;
+            self.closest = closest;
+        } else if distance == self.distance {
+            self.closest = Closest::Tied;
         }
     }
 
-    pub fn total_distance(&self) -> i32 {
-        self.distances.values().sum()
+    pub fn get_sole_closest_node(&self) -> Option<usize> {
+        match self.closest {
+            Closest::Sole(index) => Some(index),
+            Closest::Tied => None,
+        }
     }
 }
 
-pub type Grid = Vec<GridPoint>;
+// The puzzle's example nodes, shared by the tests.
+#[cfg(test)]
+pub const EXAMPLE: &str = "1, 1\n1, 6\n8, 3\n3, 4\n5, 5\n8, 9";
+
+// Blank lines are skipped, and the first malformed line is reported by number.
+pub fn get_node_list<const N: usize>(input: &str) -> Result<NodeList<N>, String> {
+    let mut node_list: NodeList<N> = vec![];
+    for (index, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
 
-pub fn get_node_list(input: &str) -> Result<NodeList, String> {
-    let mut node_list: NodeList = vec![];
-    for line in input.lines() {
-        node_list.push(InputNode::from_str(line).map_err(|e| e.to_string())?);
+        node_list
+            .push(InputNode::from_str(line).map_err(|e| format!("Line {}: {}", index + 1, e))?);
     }
 
     Ok(node_list)
 }
 
-pub fn calculate_distances(node_list: &NodeList, total_area: &Area) -> Box<Grid> {
-    let mut grid: Grid = vec![];
-    for curr_x in total_area.min_x()..=total_area.max_x() {
-        for curr_y in total_area.min_y()..=total_area.max_y() {
-            let curr_point = Point::new(curr_x, curr_y);
-
-            let mut curr_grid_point =
-                GridPoint::new(is_exterior_point(&curr_point, &total_area), HashMap::new());
-
-            for node in node_list.iter() {
-                curr_grid_point.insert_distance(*node, manhattan_distance(&curr_point, &node));
-            }
+// The number of coordinates on the first non-blank line.
+pub fn get_dimensions(input: &str) -> Result<usize, String> {
+    input
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .map(|line| line.split(',').count())
+        .ok_or_else(|| "No nodes in input".to_owned())
+}
 
-            grid.push(curr_grid_point);
-        }
+// Finds the closest node to every point in the area, one row (fixed y) at a time,
+// so only a single row of grid points is ever held in memory.
+pub fn calculate_distances<F>(
+    metric: &dyn Metric,
+    node_list: &NodeList,
+    total_area: &Area,
+    mut on_row: F,
+) where
+    F: FnMut(i32, &[GridPoint]),
+{
+    let width = (total_area.max_x() - total_area.min_x() + 1) as usize;
+    let mut row: Vec<GridPoint> = Vec::with_capacity(width);
+
+    for curr_y in total_area.min_y()..=total_area.max_y() {
+        row.clear();
+        metric.closest_in_row(
+            node_list,
+            curr_y,
+            total_area.min_x(),
+            total_area.max_x(),
+            &mut row,
+        );
+        on_row(curr_y, &row);
     }
+}
 
-    Box::new(grid)
+pub fn get_sole_closest_node(
+    metric: &dyn Metric,
+    point: &Point,
+    node_list: &NodeList,
+) -> Option<usize> {
+    let mut grid_point = GridPoint::unreached();
+    for (index, node) in node_list.iter().enumerate() {
+        grid_point.offer(metric.distance(point, node), Closest::Sole(index));
+    }
+    grid_point.get_sole_closest_node()
 }
 
-fn is_exterior_point(point: &Point, area: &Area) -> bool {
-    point.x == area.min_x()
-        || point.x == area.max_x()
-        || point.y == area.min_y()
-        || point.y == area.max_y()
+pub fn manhattan_distance<const N: usize>(p1: &Point<N>, p2: &Point<N>) -> i32 {
+    p1.coords
+        .iter()
+        .zip(p2.coords.iter())
+        .map(|(c1, c2)| (c1 - c2).abs())
+        .sum()
 }
 
-pub fn manhattan_distance(p1: &Point, p2: &Point) -> i32 {
-    (p1.x - p2.x).abs() + (p1.y - p2.y).abs()
+#[cfg(test)]
+mod test_get_node_list {
+    use super::{get_dimensions, get_node_list, NodeList, Point};
+
+    #[test]
+    fn flexible_whitespace() {
+        let node_list: NodeList = get_node_list("1,2\n  3 ,\t-4  \n\n5, 6\n").unwrap();
+        assert_eq!(
+            node_list,
+            vec![Point::new(1, 2), Point::new(3, -4), Point::new(5, 6)]
+        );
+    }
+
+    #[test]
+    fn any_dimensions() {
+        let node_list: NodeList<4> = get_node_list("1, 2, 3, 4\n-1,0,0,9").unwrap();
+        assert_eq!(node_list[1].coords, [-1, 0, 0, 9]);
+        assert_eq!(get_dimensions("\n1, 2, 3, 4\n").unwrap(), 4);
+        assert!(get_dimensions("\n").is_err());
+    }
+
+    #[test]
+    fn malformed_lines() {
+        assert_eq!(
+            get_node_list::<2>("1, 2\n3").unwrap_err(),
+            "Line 2: expected 2 coordinates, found 1"
+        );
+        assert_eq!(
+            get_node_list::<3>("1, 2, 3\n4, 5, 6, 7").unwrap_err(),
+            "Line 2: expected 3 coordinates, found 4"
+        );
+        assert!(get_node_list::<2>("1, 2\n\n3, x")
+            .unwrap_err()
+            .starts_with("Line 3: invalid coordinate 'x'"));
+        assert!(get_node_list::<2>("1 2").is_err());
+    }
 }
 
 #[cfg(test)]
@@ -236,3 +356,67 @@ mod test_manhattan_distance {
         );
     }
 }
+
+#[cfg(test)]
+mod test_calculate_distances {
+    use super::{calculate_distances, get_node_list, Area, Point, EXAMPLE};
+    use crate::metric::{Chebyshev, EuclideanSquared, Manhattan, Metric, WeightedManhattan};
+
+    fn assert_matches_brute_force(metric: &dyn Metric) {
+        // The example, with a repeated node and one far from the others.
+        let node_list = get_node_list(&(EXAMPLE.to_owned() + "\n3, 4\n20, -3")).unwrap();
+        let area = Area::new(-5, -5, 25, 15);
+
+        let mut rows = 0;
+        calculate_distances(metric, &node_list, &area, |curr_y, row| {
+            rows += 1;
+            assert_eq!(row.len(), 31);
+
+            for (offset, grid_point) in row.iter().enumerate() {
+                let curr_point = Point::new(area.min_x() + offset as i32, curr_y);
+                let distances: Vec<i64> = node_list
+                    .iter()
+                    .map(|node| metric.distance(&curr_point, node))
+                    .collect();
+                let shortest = *distances.iter().min().unwrap();
+                let closest: Vec<usize> = (0..distances.len())
+                    .filter(|index| distances[*index] == shortest)
+                    .collect();
+
+                assert_eq!(grid_point.distance(), shortest);
+                if closest.len() == 1 {
+                    assert_eq!(grid_point.get_sole_closest_node(), Some(closest[0]));
+                } else {
+                    assert_eq!(grid_point.get_sole_closest_node(), None);
+                }
+            }
+        });
+
+        assert_eq!(rows, 21);
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        assert_matches_brute_force(&Manhattan);
+        assert_matches_brute_force(&WeightedManhattan::new(3, 2).unwrap());
+        assert_matches_brute_force(&Chebyshev);
+        assert_matches_brute_force(&EuclideanSquared);
+    }
+
+    #[test]
+    fn single_node() {
+        let node_list = get_node_list("2, 2").unwrap();
+        calculate_distances(&Manhattan, &node_list, &Area::new(0, 0, 4, 4), |_, row| {
+            assert!(row
+                .iter()
+                .all(|grid_point| grid_point.get_sole_closest_node() == Some(0)));
+        });
+
+        let mut closest = vec![];
+        calculate_distances(&Manhattan, &node_list, &Area::new(2, 2, 2, 2), |_, row| {
+            closest.push(row[0]);
+        });
+        assert_eq!(closest[0].distance(), 0);
+        assert_eq!(closest[0].get_sole_closest_node(), Some(0));
+    }
+}
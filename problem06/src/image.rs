@@ -0,0 +1,152 @@
+use crate::helpers::*;
+use crate::metric::Metric;
+
+const TIED: Rgb = [0, 0, 0];
+const HATCH_SPACING: i32 = 6;
+
+type Rgb = [u8; 3];
+
+// Renders each point's closest node as a binary PPM (P6) image, one pixel per
+// point, for comparing region shapes between metrics:
+//   - each node's region gets its own colour, with the node itself drawn in white
+//   - points with tied closest nodes are black
+//   - infinite regions are hatched with darker diagonal lines
+//   - points with a total distance under `max_distance` (the part 2 safe region)
+//     are blended towards white
+pub fn render_regions(
+    metric: &dyn Metric,
+    node_list: &NodeList,
+    area: &Area,
+    max_distance: i64,
+) -> Vec<u8> {
+    let width = area.max_x() - area.min_x() + 1;
+    let height = area.max_y() - area.min_y() + 1;
+    let mut image = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+
+    let colours: Vec<Rgb> = (0..node_list.len())
+        .map(|index| node_colour(index, node_list.len()))
+        .collect();
+    let is_infinite = metric.infinite_nodes(node_list);
+
+    calculate_distances(metric, node_list, area, |curr_y, row| {
+        for (offset, grid_point) in row.iter().enumerate() {
+            let curr_point = Point::new(area.min_x() + offset as i32, curr_y);
+            let mut colour = match grid_point.get_sole_closest_node() {
+                Some(_) if grid_point.distance() == 0 => [255, 255, 255],
+                Some(closest) if is_infinite[closest] && is_hatched(&curr_point) => {
+                    scale(colours[closest], 0.5)
+                }
+                Some(closest) => colours[closest],
+                None => TIED,
+            };
+
+            let total_distance: i64 = node_list
+                .iter()
+                .map(|node| metric.distance(&curr_point, node))
+                .sum();
+            if total_distance < max_distance {
+                colour = blend_with_white(colour);
+            }
+
+            image.extend_from_slice(&colour);
+        }
+    });
+
+    image
+}
+
+fn is_hatched(point: &Point) -> bool {
+    (point.x() + point.y()).rem_euclid(HATCH_SPACING) == 0
+}
+
+// Spreads the hues evenly around the colour wheel, alternating between two
+// brightnesses so that neighbouring indices are easier to tell apart.
+fn node_colour(index: usize, node_count: usize) -> Rgb {
+    let hue = index as f64 / node_count as f64 * 6.0;
+    let value = if index.is_multiple_of(2) { 1.0 } else { 0.7 };
+    let rising = hue.fract();
+
+    let (red, green, blue) = match hue as u32 {
+        0 => (1.0, rising, 0.0),
+        1 => (1.0 - rising, 1.0, 0.0),
+        2 => (0.0, 1.0, rising),
+        3 => (0.0, 1.0 - rising, 1.0),
+        4 => (rising, 0.0, 1.0),
+        _ => (1.0, 0.0, 1.0 - rising),
+    };
+
+    scale(
+        [
+            (red * 255.0) as u8,
+            (green * 255.0) as u8,
+            (blue * 255.0) as u8,
+        ],
+        value,
+    )
+}
+
+fn scale(colour: Rgb, factor: f64) -> Rgb {
+    [
+        (f64::from(colour[0]) * factor) as u8,
+        (f64::from(colour[1]) * factor) as u8,
+        (f64::from(colour[2]) * factor) as u8,
+    ]
+}
+
+fn blend_with_white(colour: Rgb) -> Rgb {
+    [
+        colour[0] / 2 + 128,
+        colour[1] / 2 + 128,
+        colour[2] / 2 + 128,
+    ]
+}
+
+#[cfg(test)]
+mod test_render_regions {
+    use super::{node_colour, render_regions, TIED};
+    use crate::helpers::{get_node_list, Area};
+    use crate::metric::Manhattan;
+
+    const HEADER: &[u8] = b"P6\n5 3\n255\n";
+
+    fn pixel(image: &[u8], x: usize, y: usize) -> [u8; 3] {
+        let start = HEADER.len() + (y * 5 + x) * 3;
+        [image[start], image[start + 1], image[start + 2]]
+    }
+
+    #[test]
+    fn ties_and_nodes() {
+        let node_list = get_node_list("0, 1\n4, 1").unwrap();
+        let image = render_regions(&Manhattan, &node_list, &Area::new(0, 0, 4, 2), 0);
+
+        assert!(image.starts_with(HEADER));
+        assert_eq!(image.len(), HEADER.len() + 5 * 3 * 3);
+        assert_eq!(pixel(&image, 0, 1), [255, 255, 255]);
+        assert_eq!(pixel(&image, 2, 0), TIED);
+        assert_eq!(pixel(&image, 2, 2), TIED);
+        assert_eq!(pixel(&image, 3, 1), node_colour(1, 2));
+    }
+
+    #[test]
+    fn hatching_and_safe_region() {
+        let node_list = get_node_list("0, 1\n4, 1").unwrap();
+        let image = render_regions(&Manhattan, &node_list, &Area::new(0, 0, 4, 2), 6);
+
+        // Both regions are infinite, so every sixth diagonal is darkened.
+        let [red, green, blue] = node_colour(0, 2);
+        assert_eq!(pixel(&image, 0, 0), [red / 2, green / 2, blue / 2]);
+
+        // Between the nodes, only the middle row has a total distance under 6.
+        assert_eq!(pixel(&image, 2, 1), [128, 128, 128]);
+        assert_eq!(pixel(&image, 2, 0), [0, 0, 0]);
+    }
+
+    #[test]
+    fn distinct_colours() {
+        let colours: Vec<[u8; 3]> = (0..50).map(|index| node_colour(index, 50)).collect();
+        for (index, colour) in colours.iter().enumerate() {
+            assert!(!colours[index + 1..].contains(colour));
+            assert_ne!(*colour, TIED);
+        }
+    }
+}
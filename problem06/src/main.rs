@@ -1,16 +1,119 @@
-use crate::helpers::get_node_list;
+use crate::helpers::{get_dimensions, get_node_list, Area, NodeList};
+use crate::image::render_regions;
+use crate::metric::{get_metric, Manhattan, Metric};
 use crate::part1::*;
 use crate::part2::*;
 use std::fs;
 
 mod helpers;
+mod image;
+mod metric;
 mod part1;
 mod part2;
 
 fn main() -> Result<(), String> {
     let input = fs::read_to_string("input/data.txt").map_err(|e| e.to_string())?;
-    let node_list = get_node_list(&input)?;
-    part1(&node_list);
-    part2(&node_list);
+    let metric: Box<dyn Metric> = match get_arg_value("--metric")? {
+        Some(name) => get_metric(&name)?,
+        None => Box::new(Manhattan),
+    };
+
+    let max_distance = match get_arg_value("--max-distance")? {
+        Some(max_distance) => max_distance
+            .parse()
+            .map_err(|e| format!("--max-distance: {}", e))?,
+        None => DEFAULT_MAX_DISTANCE,
+    };
+
+    // Points have their number of dimensions fixed when compiled, so each supported
+    // number is listed here. Beyond six, part 1's bounding box would rarely be small
+    // enough to search.
+    match get_dimensions(&input)? {
+        2 => run_in_plane(metric.as_ref(), &get_node_list(&input)?, max_distance),
+        1 => run_in_dimensions::<1>(metric.as_ref(), &input, max_distance),
+        3 => run_in_dimensions::<3>(metric.as_ref(), &input, max_distance),
+        4 => run_in_dimensions::<4>(metric.as_ref(), &input, max_distance),
+        5 => run_in_dimensions::<5>(metric.as_ref(), &input, max_distance),
+        6 => run_in_dimensions::<6>(metric.as_ref(), &input, max_distance),
+        dimensions => Err(format!("Unsupported number of dimensions: {}", dimensions)),
+    }
+}
+
+fn run_in_plane(
+    metric: &dyn Metric,
+    node_list: &NodeList,
+    max_distance: i64,
+) -> Result<(), String> {
+    part1(metric, node_list);
+    part2(metric, node_list, max_distance);
+
+    if let Some(path) = get_arg_value("--image")? {
+        // Covers both the finite regions and the part 2 safe region.
+        let regions = metric.finite_area_bounds(node_list);
+        let safe = calculate_max_possible_area(metric, node_list, max_distance);
+        let area = Area::new(
+            regions.min_x().min(safe.min_x()),
+            regions.min_y().min(safe.min_y()),
+            regions.max_x().max(safe.max_x()),
+            regions.max_y().max(safe.max_y()),
+        );
+        let image = render_regions(metric, node_list, &area, max_distance);
+        fs::write(&path, image).map_err(|e| e.to_string())?;
+        println!("Wrote {:?} image to {}", area, path);
+    }
+
     Ok(())
 }
+
+// The other metrics, `Area` and the image are all two-dimensional, so points with
+// any other number of dimensions only support Manhattan distance and no image.
+fn run_in_dimensions<const N: usize>(
+    metric: &dyn Metric,
+    input: &str,
+    max_distance: i64,
+) -> Result<(), String> {
+    if !metric.is_manhattan() {
+        return Err(format!(
+            "--metric {} needs 2-dimensional points, but these have {}; only manhattan is supported",
+            metric.name(),
+            N
+        ));
+    }
+    if get_arg_value("--image")?.is_some() {
+        return Err(format!(
+            "--image needs 2-dimensional points, but these have {}",
+            N
+        ));
+    }
+
+    let node_list: NodeList<N> = get_node_list(input)?;
+    println!(
+        "Part 1 largest area ({} dimensions) = {}",
+        N,
+        find_largest_manhattan_area(&node_list)?
+    );
+    println!(
+        "Part 2 area ({} dimensions, under {}) = {}",
+        N,
+        max_distance,
+        find_manhattan_area(&node_list, max_distance)?
+    );
+    Ok(())
+}
+
+// Reads the value following a command line flag. Supported flags are:
+//   --metric <name>     measure distances with manhattan (the default), chebyshev,
+//                       euclidean-squared or weighted-manhattan:<x weight>,<y weight>
+//   --max-distance <n>  the total distance part 2's region must stay under (10000)
+//   --image <file>      write the closest-node regions as a PPM image
+// Points with other than 2 dimensions can only use manhattan, and no --image.
+fn get_arg_value(flag: &str) -> Result<Option<String>, String> {
+    let mut args = std::env::args().skip_while(|arg| arg != flag);
+    match args.next() {
+        None => Ok(None),
+        Some(_) => args
+            .next()
+            .map(Some)
+            .ok_or_else(|| format!("{} requires a value", flag)),
+    }
+}
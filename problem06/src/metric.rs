@@ -0,0 +1,568 @@
+use std::cmp;
+
+use crate::helpers::*;
+
+pub trait Metric {
+    fn name(&self) -> String;
+
+    fn distance(&self, p1: &Point, p2: &Point) -> i64;
+
+    // Fills `row` with the closest node to each point from `min_x` to `max_x` along
+    // the row at `curr_y`. By default every node is checked for every point.
+    fn closest_in_row(
+        &self,
+        node_list: &NodeList,
+        curr_y: i32,
+        min_x: i32,
+        max_x: i32,
+        row: &mut Vec<GridPoint>,
+    ) {
+        for curr_x in min_x..=max_x {
+            let curr_point = Point::new(curr_x, curr_y);
+            let mut grid_point = GridPoint::unreached();
+            for (index, node) in node_list.iter().enumerate() {
+                grid_point.offer(self.distance(&curr_point, node), Closest::Sole(index));
+            }
+            row.push(grid_point);
+        }
+    }
+
+    // Whether each node is the sole closest node to infinitely many points.
+    fn infinite_nodes(&self, node_list: &NodeList) -> Vec<bool>;
+
+    // An area holding every point closest to a node with a finite area. By default
+    // that is the nodes' bounding box.
+    fn finite_area_bounds(&self, node_list: &NodeList) -> Area {
+        Area::from_node_list(node_list)
+    }
+
+    // The x and y weights for metrics that are a weighted sum of the distances
+    // along each axis, which lets part 2 count each axis separately.
+    fn axis_weights(&self) -> Option<(i64, i64)> {
+        None
+    }
+
+    // Whether this is the puzzle's plain Manhattan distance, however it is named.
+    fn is_manhattan(&self) -> bool {
+        self.axis_weights() == Some((1, 1))
+    }
+}
+
+pub fn get_metric(name: &str) -> Result<Box<dyn Metric>, String> {
+    let mut parts = name.splitn(2, ':');
+    match (parts.next(), parts.next()) {
+        (Some("manhattan"), None) => Ok(Box::new(Manhattan)),
+        (Some("chebyshev"), None) => Ok(Box::new(Chebyshev)),
+        (Some("euclidean-squared"), None) => Ok(Box::new(EuclideanSquared)),
+        (Some("weighted-manhattan"), Some(weights)) => {
+            let weights: Vec<&str> = weights.split(',').collect();
+            if weights.len() != 2 {
+                return Err(format!("Expected two weights in '{}'", name));
+            }
+
+            let x_weight = weights[0].trim().parse::<i64>().map_err(|e| e.to_string())?;
+            let y_weight = weights[1].trim().parse::<i64>().map_err(|e| e.to_string())?;
+            Ok(Box::new(WeightedManhattan::new(x_weight, y_weight)?))
+        }
+        _ => Err(format!(
+            "Unknown metric '{}', expected manhattan, chebyshev, euclidean-squared or weighted-manhattan:<x>,<y>",
+            name
+        )),
+    }
+}
+
+pub struct Manhattan;
+
+impl Metric for Manhattan {
+    fn name(&self) -> String {
+        "manhattan".to_owned()
+    }
+
+    fn distance(&self, p1: &Point, p2: &Point) -> i64 {
+        i64::from(manhattan_distance(p1, p2))
+    }
+
+    fn closest_in_row(
+        &self,
+        node_list: &NodeList,
+        curr_y: i32,
+        min_x: i32,
+        max_x: i32,
+        row: &mut Vec<GridPoint>,
+    ) {
+        WeightedManhattan::new(1, 1)
+            .unwrap()
+            .closest_in_row(node_list, curr_y, min_x, max_x, row)
+    }
+
+    fn infinite_nodes(&self, node_list: &NodeList) -> Vec<bool> {
+        infinite_nodes_on_bounding_box(node_list, |p1, p2| self.distance(p1, p2))
+    }
+
+    fn axis_weights(&self) -> Option<(i64, i64)> {
+        Some((1, 1))
+    }
+}
+
+// Manhattan distance with each axis scaled by a positive weight.
+pub struct WeightedManhattan {
+    x_weight: i64,
+    y_weight: i64,
+}
+
+impl WeightedManhattan {
+    pub fn new(x_weight: i64, y_weight: i64) -> Result<Self, String> {
+        if x_weight <= 0 || y_weight <= 0 {
+            return Err("Weights must be positive".to_owned());
+        }
+
+        Ok(WeightedManhattan { x_weight, y_weight })
+    }
+}
+
+impl Metric for WeightedManhattan {
+    fn name(&self) -> String {
+        format!("weighted-manhattan:{},{}", self.x_weight, self.y_weight)
+    }
+
+    fn distance(&self, p1: &Point, p2: &Point) -> i64 {
+        self.x_weight * i64::from((p1.x() - p2.x()).abs())
+            + self.y_weight * i64::from((p1.y() - p2.y()).abs())
+    }
+
+    // Along a row, each node's distance is `wx * |x - node.x| + wy * |y - node.y|`,
+    // where the second term is fixed, so `closest_along_line` can sweep the row.
+    fn closest_in_row(
+        &self,
+        node_list: &NodeList,
+        curr_y: i32,
+        min_x: i32,
+        max_x: i32,
+        row: &mut Vec<GridPoint>,
+    ) {
+        let mut nodes: Vec<(usize, i32, i64)> = node_list
+            .iter()
+            .enumerate()
+            .map(|(index, node)| {
+                (
+                    index,
+                    node.x(),
+                    self.y_weight * i64::from((node.y() - curr_y).abs()),
+                )
+            })
+            .collect();
+        nodes.sort_by_key(|(_, x, _)| *x);
+        closest_along_line(&nodes, self.x_weight, min_x, max_x, row);
+    }
+
+    fn infinite_nodes(&self, node_list: &NodeList) -> Vec<bool> {
+        infinite_nodes_on_bounding_box(node_list, |p1, p2| self.distance(p1, p2))
+    }
+
+    fn axis_weights(&self) -> Option<(i64, i64)> {
+        Some((self.x_weight, self.y_weight))
+    }
+}
+
+// Pushes the closest node to each point from `min_x` to `max_x` along a line onto
+// `row`, where each node's distance is `weight * |x - position| + offset`, a V shape
+// in x. `nodes` holds each node's index in the node list, position and offset, and
+// must be sorted by position.
+//
+// The lower envelope of the V's is found with one sweep from the left, over the
+// nodes at or left of x, and one from the right, over the nodes right of x. That
+// takes O(width + nodes) per line rather than O(width * nodes).
+pub fn closest_along_line(
+    nodes: &[(usize, i32, i64)],
+    weight: i64,
+    min_x: i32,
+    max_x: i32,
+    row: &mut Vec<GridPoint>,
+) {
+    let start = row.len();
+
+    // Distances from the left are `weight * x + (offset - weight * position)`, so
+    // the best node so far is the one with the lowest value in brackets.
+    let mut best_left = GridPoint::unreached();
+    let mut next_node = 0;
+    for curr_x in min_x..=max_x {
+        while next_node < nodes.len() && nodes[next_node].1 <= curr_x {
+            let (index, position, offset) = nodes[next_node];
+            best_left.offer(offset - weight * i64::from(position), Closest::Sole(index));
+            next_node += 1;
+        }
+
+        let mut grid_point = GridPoint::unreached();
+        if next_node > 0 {
+            grid_point.offer(
+                weight * i64::from(curr_x) + best_left.distance(),
+                best_left.closest(),
+            );
+        }
+        row.push(grid_point);
+    }
+
+    // Distances from the right are `(offset + weight * position) - weight * x`.
+    let mut best_right = GridPoint::unreached();
+    let mut next_node = nodes.len();
+    for curr_x in (min_x..=max_x).rev() {
+        while next_node > 0 && nodes[next_node - 1].1 > curr_x {
+            let (index, position, offset) = nodes[next_node - 1];
+            best_right.offer(offset + weight * i64::from(position), Closest::Sole(index));
+            next_node -= 1;
+        }
+
+        if next_node < nodes.len() {
+            row[start + (curr_x - min_x) as usize].offer(
+                best_right.distance() - weight * i64::from(curr_x),
+                best_right.closest(),
+            );
+        }
+    }
+}
+
+pub struct Chebyshev;
+
+impl Metric for Chebyshev {
+    fn name(&self) -> String {
+        "chebyshev".to_owned()
+    }
+
+    fn distance(&self, p1: &Point, p2: &Point) -> i64 {
+        i64::from(cmp::max((p1.x() - p2.x()).abs(), (p1.y() - p2.y()).abs()))
+    }
+
+    // Rotating by 45 degrees, with `u = x + y` and `v = x - y`, Chebyshev distance is
+    // `(|du| + |dv|) / 2`. As for Manhattan distance, a point beyond every node in u
+    // can step a further 2 in u, via (x + 1, y + 1), adding 1 to every node's
+    // distance without changing which is closest, and likewise for v. So a node with
+    // infinitely many closest points has one within a step of the nodes' bounding
+    // box in (u, v), and only those points need checking.
+    fn infinite_nodes(&self, node_list: &NodeList) -> Vec<bool> {
+        let mut is_infinite = vec![false; node_list.len()];
+        if node_list.is_empty() {
+            return is_infinite;
+        }
+
+        let rotated: NodeList = node_list
+            .iter()
+            .map(|node| Point::new(node.x() + node.y(), node.x() - node.y()))
+            .collect();
+        let bounds = Area::from_node_list(&rotated);
+        let (min_u, max_u) = (bounds.min_x() - 1, bounds.max_x() + 1);
+        let (min_v, max_v) = (bounds.min_y() - 1, bounds.max_y() + 1);
+
+        let mut rotated_border = vec![];
+        for u in min_u..=max_u {
+            for v in min_v..=max_v {
+                let on_border =
+                    u <= min_u + 1 || u >= max_u - 1 || v <= min_v + 1 || v >= max_v - 1;
+                if on_border && (u + v) % 2 == 0 {
+                    rotated_border.push(Point::new((u + v) / 2, (u - v) / 2));
+                }
+            }
+        }
+
+        mark_sole_closest(self, node_list, rotated_border.iter(), &mut is_infinite);
+        is_infinite
+    }
+
+    // By the same argument, finite areas lie within the nodes' bounding box in
+    // (u, v), which is a diamond in (x, y).
+    fn finite_area_bounds(&self, node_list: &NodeList) -> Area {
+        let rotated: NodeList = node_list
+            .iter()
+            .map(|node| Point::new(node.x() + node.y(), node.x() - node.y()))
+            .collect();
+        let bounds = Area::from_node_list(&rotated);
+        Area::new(
+            (bounds.min_x() + bounds.min_y()).div_euclid(2),
+            (bounds.min_x() - bounds.max_y()).div_euclid(2),
+            (bounds.max_x() + bounds.max_y() + 1).div_euclid(2),
+            (bounds.max_x() - bounds.min_y() + 1).div_euclid(2),
+        )
+    }
+}
+
+pub struct EuclideanSquared;
+
+impl Metric for EuclideanSquared {
+    fn name(&self) -> String {
+        "euclidean-squared".to_owned()
+    }
+
+    fn distance(&self, p1: &Point, p2: &Point) -> i64 {
+        let dx = i64::from(p1.x() - p2.x());
+        let dy = i64::from(p1.y() - p2.y());
+        dx * dx + dy * dy
+    }
+
+    // Moving away from the nodes changes each node's Euclidean distance by a
+    // different amount, so the bounding box tells us nothing. Instead, a node's
+    // region is unbounded exactly when the node is on the boundary of the convex
+    // hull of all nodes, and an unbounded region always holds infinitely many
+    // integer points.
+    fn infinite_nodes(&self, node_list: &NodeList) -> Vec<bool> {
+        let hull = convex_hull(node_list);
+        if hull.len() < 3 {
+            return vec![true; node_list.len()];
+        }
+
+        node_list
+            .iter()
+            .map(|node| {
+                (0..hull.len())
+                    .any(|index| is_on_segment(node, &hull[index], &hull[(index + 1) % hull.len()]))
+            })
+            .collect()
+    }
+
+    // A finite area is a convex polygon whose corners are points equally far from
+    // three nodes, with no node any closer. So finite areas lie within the box
+    // around the nodes and every such corner.
+    fn finite_area_bounds(&self, node_list: &NodeList) -> Area {
+        let bounds = Area::from_node_list(node_list);
+        let (mut min_x, mut min_y) = (f64::from(bounds.min_x()), f64::from(bounds.min_y()));
+        let (mut max_x, mut max_y) = (f64::from(bounds.max_x()), f64::from(bounds.max_y()));
+
+        for (index, node1) in node_list.iter().enumerate() {
+            for (offset, node2) in node_list[index + 1..].iter().enumerate() {
+                for node3 in node_list[index + offset + 2..].iter() {
+                    let (x, y) = match circumcentre(node1, node2, node3) {
+                        Some(centre) => centre,
+                        None => continue,
+                    };
+
+                    let radius = squared_distance_to(x, y, node1);
+                    let is_corner = node_list
+                        .iter()
+                        .all(|node| squared_distance_to(x, y, node) >= radius * (1.0 - 1e-9));
+                    if is_corner {
+                        min_x = min_x.min(x);
+                        min_y = min_y.min(y);
+                        max_x = max_x.max(x);
+                        max_y = max_y.max(y);
+                    }
+                }
+            }
+        }
+
+        Area::new(
+            min_x.floor() as i32,
+            min_y.floor() as i32,
+            max_x.ceil() as i32,
+            max_y.ceil() as i32,
+        )
+    }
+}
+
+// The point equally far from all three points, if they are not collinear.
+fn circumcentre(a: &Point, b: &Point, c: &Point) -> Option<(f64, f64)> {
+    let determinant = 2 * cross(a, b, c);
+    if determinant == 0 {
+        return None;
+    }
+
+    let (bx, by) = (i64::from(b.x() - a.x()), i64::from(b.y() - a.y()));
+    let (cx, cy) = (i64::from(c.x() - a.x()), i64::from(c.y() - a.y()));
+    let (b_squared, c_squared) = (bx * bx + by * by, cx * cx + cy * cy);
+    let determinant = determinant as f64;
+    Some((
+        f64::from(a.x()) + (cy * b_squared - by * c_squared) as f64 / determinant,
+        f64::from(a.y()) + (bx * c_squared - cx * b_squared) as f64 / determinant,
+    ))
+}
+
+fn squared_distance_to(x: f64, y: f64, node: &Point) -> f64 {
+    (x - f64::from(node.x())).powi(2) + (y - f64::from(node.y())).powi(2)
+}
+
+// Manhattan-like distances grow by the same amount for every node when moving
+// away from the nodes along an axis, so a point beyond the nodes' bounding box can
+// be moved back onto it without changing its closest node. So a node with
+// infinitely many closest points has one on the surface of the bounding box, and
+// only those points need checking. This holds in any number of dimensions.
+pub fn infinite_nodes_on_bounding_box<const N: usize, F>(
+    node_list: &NodeList<N>,
+    distance: F,
+) -> Vec<bool>
+where
+    F: Fn(&Point<N>, &Point<N>) -> i64,
+{
+    let mut is_infinite = vec![false; node_list.len()];
+    let (min, max) = match bounding_box(node_list) {
+        Some(bounds) => bounds,
+        None => return is_infinite,
+    };
+
+    // A line along the first axis is on the surface throughout when any other axis
+    // is at its limit, and otherwise only at its ends.
+    let mut line_start = Point { coords: min };
+    loop {
+        let on_surface = (1..N).any(|axis| {
+            line_start.coords[axis] == min[axis] || line_start.coords[axis] == max[axis]
+        });
+        let step = match on_surface {
+            true => 1,
+            false => cmp::max(max[0] - min[0], 1) as usize,
+        };
+
+        let mut point = line_start;
+        for curr_x in (min[0]..=max[0]).step_by(step) {
+            point.coords[0] = curr_x;
+            let mut grid_point = GridPoint::unreached();
+            for (index, node) in node_list.iter().enumerate() {
+                grid_point.offer(distance(&point, node), Closest::Sole(index));
+            }
+            if let Some(closest) = grid_point.get_sole_closest_node() {
+                is_infinite[closest] = true;
+            }
+        }
+
+        if !next_line(&mut line_start, &min, &max) {
+            break;
+        }
+    }
+
+    is_infinite
+}
+
+fn mark_sole_closest<'a, I>(
+    metric: &dyn Metric,
+    node_list: &NodeList,
+    points: I,
+    marked: &mut [bool],
+) where
+    I: Iterator<Item = &'a Point>,
+{
+    for point in points {
+        if let Some(closest) = get_sole_closest_node(metric, point, node_list) {
+            marked[closest] = true;
+        }
+    }
+}
+
+// The corners of the convex hull, in anticlockwise order, by Andrew's monotone chain.
+fn convex_hull(node_list: &NodeList) -> Vec<Point> {
+    let mut points: Vec<Point> = node_list.clone();
+    points.sort_by_key(|point| (point.x(), point.y()));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+
+    let mut hull: Vec<Point> = vec![];
+    for pass in 0..2 {
+        let start = hull.len();
+        for point in points.iter() {
+            while hull.len() >= start + 2
+                && cross(&hull[hull.len() - 2], &hull[hull.len() - 1], point) <= 0
+            {
+                hull.pop();
+            }
+            hull.push(*point);
+        }
+        hull.pop();
+
+        if pass == 0 {
+            points.reverse();
+        }
+    }
+
+    hull
+}
+
+// Positive when `a -> b -> c` turns anticlockwise, zero when they are collinear.
+fn cross(a: &Point, b: &Point, c: &Point) -> i64 {
+    i64::from(b.x() - a.x()) * i64::from(c.y() - a.y())
+        - i64::from(b.y() - a.y()) * i64::from(c.x() - a.x())
+}
+
+fn is_on_segment(point: &Point, start: &Point, end: &Point) -> bool {
+    cross(start, end, point) == 0
+        && cmp::min(start.x(), end.x()) <= point.x()
+        && point.x() <= cmp::max(start.x(), end.x())
+        && cmp::min(start.y(), end.y()) <= point.y()
+        && point.y() <= cmp::max(start.y(), end.y())
+}
+
+#[cfg(test)]
+mod test_infinite_nodes {
+    use super::{Chebyshev, EuclideanSquared, Manhattan, Metric, WeightedManhattan};
+    use crate::helpers::{calculate_distances, get_node_list, Area, EXAMPLE};
+
+    // Checks the detected infinite nodes against areas counted over a small and a
+    // much larger grid: finite areas stay the same size, infinite ones grow. Finite
+    // areas must also fit within the metric's bounds for them.
+    fn assert_infinite_nodes_grow(metric: &dyn Metric, input: &str) {
+        let node_list = get_node_list(input).unwrap();
+        let count = |area: &Area| {
+            let mut totals = vec![0; node_list.len()];
+            calculate_distances(metric, &node_list, area, |_, row| {
+                for grid_point in row.iter() {
+                    if let Some(closest) = grid_point.get_sole_closest_node() {
+                        totals[closest] += 1;
+                    }
+                }
+            });
+            totals
+        };
+        let count_with_margin = |margin: i32| {
+            let bounds = Area::from_node_list(&node_list);
+            count(&Area::new(
+                bounds.min_x() - margin,
+                bounds.min_y() - margin,
+                bounds.max_x() + margin,
+                bounds.max_y() + margin,
+            ))
+        };
+
+        let (small, large) = (count_with_margin(20), count_with_margin(60));
+        let bounded = count(&metric.finite_area_bounds(&node_list));
+        let is_infinite = metric.infinite_nodes(&node_list);
+        for index in 0..node_list.len() {
+            let message = format!("{} node {:?}", metric.name(), node_list[index]);
+            assert_eq!(
+                is_infinite[index],
+                large[index] > small[index],
+                "{}",
+                message
+            );
+            if !is_infinite[index] {
+                assert_eq!(bounded[index], large[index], "{}", message);
+            }
+        }
+    }
+
+    #[test]
+    fn manhattan_metrics() {
+        assert!(Manhattan.is_manhattan());
+        assert!(WeightedManhattan::new(1, 1).unwrap().is_manhattan());
+        assert!(!WeightedManhattan::new(1, 3).unwrap().is_manhattan());
+        assert!(!Chebyshev.is_manhattan());
+        assert!(!EuclideanSquared.is_manhattan());
+    }
+
+    #[test]
+    fn puzzle_example() {
+        assert_eq!(
+            Manhattan.infinite_nodes(&get_node_list(EXAMPLE).unwrap()),
+            vec![true, true, true, false, false, true]
+        );
+    }
+
+    #[test]
+    fn infinite_nodes_grow() {
+        let inputs = [
+            EXAMPLE,
+            "0, 0\n10, 0\n5, 3\n5, 8\n0, 10\n10, 10\n5, 5",
+            "0, 0\n4, 4\n8, 8\n2, 6",
+        ];
+        for input in inputs.iter() {
+            assert_infinite_nodes_grow(&Manhattan, input);
+            assert_infinite_nodes_grow(&WeightedManhattan::new(1, 3).unwrap(), input);
+            assert_infinite_nodes_grow(&Chebyshev, input);
+            assert_infinite_nodes_grow(&EuclideanSquared, input);
+        }
+    }
+}
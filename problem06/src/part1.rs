@@ -1,41 +1,184 @@
 use crate::helpers::*;
-use std::collections::{HashMap, HashSet};
+use crate::metric::{closest_along_line, infinite_nodes_on_bounding_box, Metric};
 
-pub fn part1(node_list: &NodeList) -> () {
-    let total_area: Area = Area::from_node_list(&node_list);
+pub fn part1(metric: &dyn Metric, node_list: &NodeList) {
+    let total_area: Area = metric.finite_area_bounds(node_list);
     println!("Input node total area = {:?}", total_area);
-    let grid = calculate_distances(&node_list, &total_area);
-    let largest_area = find_largest_non_infinite_area(&grid);
-    assert_eq!(4233, largest_area);
-    println!("Part 1 largest area = {}", largest_area);
+    let largest_area = find_largest_non_infinite_area(metric, node_list, &total_area);
+    if metric.is_manhattan() {
+        assert_eq!(4233, largest_area);
+    }
+    println!("Part 1 largest area ({}) = {}", metric.name(), largest_area);
 }
 
-fn find_largest_non_infinite_area(grid: &Box<Grid>) -> i32 {
-    let mut infinite_area_nodes: HashSet<InputNode> = HashSet::new();
-    let mut area_totals: HashMap<Point, i32> = HashMap::new();
+// Areas are only counted within the total area, so it must cover every finite
+// area, as `Metric::finite_area_bounds` does.
+fn find_largest_non_infinite_area(
+    metric: &dyn Metric,
+    node_list: &NodeList,
+    total_area: &Area,
+) -> u64 {
+    let is_infinite = metric.infinite_nodes(node_list);
+    let mut area_totals = vec![0; node_list.len()];
 
-    for grid_point in grid.iter() {
-        let sole_closest = grid_point.get_sole_closest_node();
-        if let Some(closest) = sole_closest {
-            if grid_point.is_exterior() {
-                infinite_area_nodes.insert(closest);
-            }
+    calculate_distances(metric, node_list, total_area, |_, row| {
+        count_sole_closest(row, &mut area_totals)
+    });
 
-            if !infinite_area_nodes.contains(&closest) {
-                let node_total = area_totals.entry(closest).or_insert(0);
-                *node_total = *node_total + 1;
-            }
+    let infinite_area_nodes: Vec<&InputNode> = node_list
+        .iter()
+        .zip(is_infinite.iter())
+        .filter(|(_, is_infinite)| **is_infinite)
+        .map(|(node, _)| node)
+        .collect();
+    println!("Infinite area nodes = {:?}", infinite_area_nodes);
+
+    largest_finite_area(area_totals, is_infinite)
+}
+
+// The most points the N-dimensional search will visit.
+pub const MAX_SEARCH_POINTS: u64 = 1 << 32;
+
+// The same search for Manhattan distance in any number of dimensions. The box is
+// swept one line along the first axis at a time, as rows are in two dimensions,
+// with each node's distance across the other axes fixed along the line. That
+// takes O(points + lines * nodes), so boxes of more than `MAX_SEARCH_POINTS`
+// points are refused rather than searched.
+pub fn find_largest_manhattan_area<const N: usize>(node_list: &NodeList<N>) -> Result<u64, String> {
+    let (min, max) = match bounding_box(node_list) {
+        Some(bounds) => bounds,
+        None => return Ok(0),
+    };
+    let points = (0..N)
+        .try_fold(1u64, |points, axis| {
+            points.checked_mul((i64::from(max[axis]) - i64::from(min[axis]) + 1) as u64)
+        })
+        .filter(|points| *points <= MAX_SEARCH_POINTS);
+    if points.is_none() {
+        return Err(format!(
+            "The nodes' bounding box has more than {} points to search",
+            MAX_SEARCH_POINTS
+        ));
+    }
+
+    let is_infinite =
+        infinite_nodes_on_bounding_box(node_list, |p1, p2| i64::from(manhattan_distance(p1, p2)));
+
+    let mut by_first_axis: Vec<usize> = (0..node_list.len()).collect();
+    by_first_axis.sort_by_key(|index| node_list[*index].coords[0]);
+    let mut area_totals = vec![0; node_list.len()];
+    let mut row: Vec<GridPoint> = Vec::with_capacity((max[0] - min[0] + 1) as usize);
+    let mut line_start = Point { coords: min };
+    loop {
+        let nodes: Vec<(usize, i32, i64)> = by_first_axis
+            .iter()
+            .map(|index| {
+                let node = &node_list[*index];
+                let offset = (1..N)
+                    .map(|axis| i64::from((node.coords[axis] - line_start.coords[axis]).abs()))
+                    .sum();
+                (*index, node.coords[0], offset)
+            })
+            .collect();
+
+        row.clear();
+        closest_along_line(&nodes, 1, min[0], max[0], &mut row);
+        count_sole_closest(&row, &mut area_totals);
+
+        if !next_line(&mut line_start, &min, &max) {
+            break;
         }
     }
 
-    println!("Infinite area nodes = {:?}", infinite_area_nodes);
-    println!("Area totals = {:?}", area_totals);
+    Ok(largest_finite_area(area_totals, is_infinite))
+}
 
-    *area_totals
-        .iter()
-        .fold(None, |max, curr| match max {
-            None => Some(curr.1),
-            Some(existing) => Some(if curr.1 > existing { curr.1 } else { existing }),
-        })
-        .unwrap()
+fn count_sole_closest(row: &[GridPoint], area_totals: &mut [u64]) {
+    for grid_point in row.iter() {
+        if let Some(closest) = grid_point.get_sole_closest_node() {
+            area_totals[closest] += 1;
+        }
+    }
+}
+
+fn largest_finite_area(area_totals: Vec<u64>, is_infinite: Vec<bool>) -> u64 {
+    area_totals
+        .into_iter()
+        .zip(is_infinite)
+        .filter(|(_, is_infinite)| !is_infinite)
+        .map(|(total, _)| total)
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test_find_largest_non_infinite_area {
+    use super::{find_largest_manhattan_area, find_largest_non_infinite_area};
+    use crate::helpers::{get_node_list, Area, NodeList, EXAMPLE};
+    use crate::metric::{Chebyshev, Manhattan, Metric};
+
+    #[test]
+    fn puzzle_example() {
+        let node_list = get_node_list(EXAMPLE).unwrap();
+        assert_eq!(
+            find_largest_non_infinite_area(
+                &Manhattan,
+                &node_list,
+                &Area::from_node_list(&node_list)
+            ),
+            17
+        );
+    }
+
+    #[test]
+    fn chebyshev_example() {
+        // The centre node is closest to the 3x3 square around it, plus one point
+        // further out in each axis direction.
+        let node_list = get_node_list("0, 0\n6, 0\n0, 6\n6, 6\n3, 3").unwrap();
+        assert_eq!(
+            find_largest_non_infinite_area(
+                &Chebyshev,
+                &node_list,
+                &Chebyshev.finite_area_bounds(&node_list)
+            ),
+            13
+        );
+    }
+
+    #[test]
+    fn any_dimensions() {
+        let node_list: NodeList = get_node_list(EXAMPLE).unwrap();
+        assert_eq!(find_largest_manhattan_area(&node_list), Ok(17));
+
+        // The centre node is closest to the points with no coordinate beyond 1.
+        let node_list: NodeList<3> =
+            get_node_list("0,0,0\n4,0,0\n-4,0,0\n0,4,0\n0,-4,0\n0,0,4\n0,0,-4").unwrap();
+        assert_eq!(find_largest_manhattan_area(&node_list), Ok(27));
+
+        let node_list: NodeList<1> = get_node_list("0\n3\n10").unwrap();
+        assert_eq!(find_largest_manhattan_area(&node_list), Ok(5));
+
+        // In four dimensions the centre node is closest to the 3^4 points within 1
+        // of it along every axis, as no other node is within 3 of those.
+        let mut input = "0,0,0,0".to_owned();
+        for axis in 0..4 {
+            for sign in [-4, 4].iter() {
+                let mut coords = [0; 4];
+                coords[axis] = *sign;
+                let coords: Vec<String> = coords.iter().map(|coord| coord.to_string()).collect();
+                input.push('\n');
+                input.push_str(&coords.join(","));
+            }
+        }
+        let node_list: NodeList<4> = get_node_list(&input).unwrap();
+        assert_eq!(find_largest_manhattan_area(&node_list), Ok(81));
+    }
+
+    #[test]
+    fn large_boxes_are_refused() {
+        let node_list: NodeList<4> = get_node_list("0,0,0,0\n1000,1000,1000,1000").unwrap();
+        assert!(find_largest_manhattan_area(&node_list).is_err());
+        let node_list: NodeList<3> = get_node_list("0,0,0\n0,0,0").unwrap();
+        assert_eq!(find_largest_manhattan_area(&node_list), Ok(0));
+    }
 }
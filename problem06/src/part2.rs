@@ -1,31 +1,544 @@
 use crate::helpers::*;
+use crate::metric::Metric;
 
-pub fn part2(node_list: &NodeList) -> () {
-    const MAX_DISTANCE: i32 = 10000;
-    let total_area = calculate_max_possible_area(node_list, MAX_DISTANCE);
-    println!("Part 2 search total area = {:?}", total_area);
-    let grid = calculate_distances(&node_list, &total_area);
-    let target_area = find_area(&grid, MAX_DISTANCE);
-    assert_eq!(target_area, 45290);
-    println!("Part 2 area = {}", find_area(&grid, MAX_DISTANCE));
+pub const DEFAULT_MAX_DISTANCE: i64 = 10000;
+
+pub fn part2(metric: &dyn Metric, node_list: &NodeList, max_distance: i64) {
+    let target_area = find_area(metric, node_list, max_distance);
+    if metric.is_manhattan() && max_distance == DEFAULT_MAX_DISTANCE {
+        assert_eq!(target_area, 45290);
+    }
+    println!(
+        "Part 2 area ({}, under {}) = {}",
+        metric.name(),
+        max_distance,
+        target_area
+    );
+}
+
+// Counts the points whose total distance to every node is under `max_distance`.
+//
+// When the metric is a weighted sum of per-axis distances, the total distance is
+// `x_weight * Sx(x) + y_weight * Sy(y)`, where `Sx(x)` sums `|x - node.x|` over
+// the nodes and `Sy(y)` likewise, so the region is counted from each axis alone
+// by `count_separable_area`, without visiting any cells. Other metrics check every
+// cell in `calculate_max_possible_area`.
+fn find_area(metric: &dyn Metric, node_list: &NodeList, max_distance: i64) -> u64 {
+    if node_list.is_empty() {
+        return 0;
+    }
+
+    match metric.axis_weights() {
+        Some((x_weight, y_weight)) => count_separable_axes(
+            &[
+                AxisDistances::new(node_list.iter().map(|node| node.x()), x_weight),
+                AxisDistances::new(node_list.iter().map(|node| node.y()), y_weight),
+            ],
+            i128::from(max_distance),
+        ),
+        None => {
+            let total_area = calculate_max_possible_area(metric, node_list, max_distance);
+            println!("Part 2 search total area = {:?}", total_area);
+            count_cells(metric, node_list, &total_area, max_distance)
+        }
+    }
+}
+
+// The most planes the N-dimensional count will add up one at a time.
+pub const MAX_SWEEP_PLANES: u64 = 1 << 24;
+
+// The same count for Manhattan distance in any number of dimensions. The two axes
+// with the widest ranges under the maximum are counted together in closed form, as
+// in two dimensions, for each combination of coordinates along the others. Those
+// combinations make up one plane each, so counts needing more than
+// `MAX_SWEEP_PLANES` of them are refused rather than swept.
+pub fn find_manhattan_area<const N: usize>(
+    node_list: &NodeList<N>,
+    max_distance: i64,
+) -> Result<u64, String> {
+    if node_list.is_empty() {
+        return Ok(0);
+    }
+
+    let max_distance = i128::from(max_distance);
+    let mut axes = vec![];
+    for axis in 0..N {
+        let distances = AxisDistances::new(node_list.iter().map(|node| node.coords[axis]), 1);
+        match distances.range_under(max_distance) {
+            Some((start, end)) => axes.push(((end - start + 1) as u64, distances)),
+            None => return Ok(0),
+        }
+    }
+    axes.sort_by_key(|(width, _)| *width);
+
+    let planes = axes[..N.saturating_sub(2)]
+        .iter()
+        .try_fold(1u64, |planes, (width, _)| planes.checked_mul(*width))
+        .filter(|planes| *planes <= MAX_SWEEP_PLANES);
+    if planes.is_none() {
+        return Err(format!(
+            "The region under {} spans more than {} planes to count",
+            max_distance, MAX_SWEEP_PLANES
+        ));
+    }
+
+    let axes: Vec<AxisDistances> = axes.into_iter().map(|(_, distances)| distances).collect();
+    Ok(count_separable_axes(&axes, max_distance))
+}
+
+// Counts the points whose totals summed over every axis are under `max_distance`.
+// Beyond two axes, each coordinate of the first axis that leaves room for the
+// others is tried in turn, so the axes with the narrowest ranges go first.
+fn count_separable_axes(axes: &[AxisDistances], max_distance: i128) -> u64 {
+    match axes {
+        [] => (max_distance > 0) as u64,
+        [axis] => match axis.range_under(max_distance) {
+            Some((start, end)) => (end - start + 1) as u64,
+            None => 0,
+        },
+        [x_axis, y_axis] => count_separable_area(x_axis, y_axis, max_distance),
+        [axis, rest @ ..] => match axis.range_under(max_distance) {
+            Some((start, end)) => (start..=end)
+                .map(|coord| count_separable_axes(rest, max_distance - axis.total(coord)))
+                .sum(),
+            None => 0,
+        },
+    }
+}
+
+// The weighted sum of distances from a coordinate to every node along one axis,
+// from the sorted node coordinates and their prefix sums in O(log n).
+struct AxisDistances {
+    sorted: Vec<i64>,
+    prefix_sums: Vec<i64>,
+    weight: i64,
+}
+
+// A stretch of coordinates over which the total distance changes linearly:
+// `offset` steps from the start of a walk, the total is `total + slope * steps`.
+struct Piece {
+    offset: i64,
+    total: i128,
+    slope: i128,
+}
+
+impl AxisDistances {
+    fn new<I>(coords: I, weight: i64) -> Self
+    where
+        I: Iterator<Item = i32>,
+    {
+        let mut sorted: Vec<i64> = coords.map(i64::from).collect();
+        sorted.sort_unstable();
+
+        let mut prefix_sums = vec![0];
+        for coord in sorted.iter() {
+            prefix_sums.push(prefix_sums[prefix_sums.len() - 1] + coord);
+        }
+
+        AxisDistances {
+            sorted,
+            prefix_sums,
+            weight,
+        }
+    }
+
+    fn total(&self, coord: i64) -> i128 {
+        let count = self.sorted.len() as i128;
+        let below = self.sorted.partition_point(|node| *node <= coord);
+        let below_sum = i128::from(self.prefix_sums[below]);
+        let above_sum = i128::from(self.prefix_sums[self.sorted.len()]) - below_sum;
+        let (coord, below) = (i128::from(coord), below as i128);
+
+        i128::from(self.weight)
+            * ((coord * below - below_sum) + (above_sum - coord * (count - below)))
+    }
+
+    // The total is convex and lowest at the median node.
+    fn median(&self) -> i64 {
+        self.sorted[self.sorted.len() / 2]
+    }
+
+    // The range of coordinates with a total under `limit`, if any.
+    //
+    // Beyond the outermost node the total grows by `weight * n` per step, so any
+    // coordinate more than `limit / (weight * n)` outside the nodes has a total of
+    // at least `limit`. That bounds the binary search for each end of the range.
+    fn range_under(&self, limit: i128) -> Option<(i64, i64)> {
+        let median = self.median();
+        if self.total(median) >= limit {
+            return None;
+        }
+
+        let margin = (limit / i128::from(self.weight * self.sorted.len() as i64) + 1) as i64;
+        let below = |coord: i64| self.total(coord) < limit;
+        Some((
+            first_where(self.sorted[0] - margin, median, below),
+            first_where(
+                median + 1,
+                self.sorted[self.sorted.len() - 1] + margin + 1,
+                |coord| !below(coord),
+            ) - 1,
+        ))
+    }
+
+    // The total only changes slope at a node, so walking away from `start` in
+    // `direction` (1 or -1) it is linear between the nodes passed. The total never
+    // decreases along the walk when it starts from the median node going up, or from
+    // just below it going down.
+    fn walk(&self, start: i64, direction: i64) -> Vec<Piece> {
+        let mut offsets: Vec<i64> = self
+            .sorted
+            .iter()
+            .map(|node| (node - start) * direction)
+            .filter(|offset| *offset > 0)
+            .collect();
+        offsets.push(0);
+        offsets.sort_unstable();
+        offsets.dedup();
+
+        offsets
+            .into_iter()
+            .map(|offset| {
+                let coord = start + offset * direction;
+                let total = self.total(coord);
+                Piece {
+                    offset,
+                    total,
+                    slope: self.total(coord + direction) - total,
+                }
+            })
+            .collect()
+    }
+}
+
+// Splits the plane into quadrants around the median node, and in each counts the
+// points by walking outwards along x. Each x contributes the number of y values
+// whose total fits in the distance left over. That shrinks as x moves away, so
+// the piece of the y walk holding the last such y only moves inwards. Within one
+// piece of each walk, the count per x is a floor of a linear function, which
+// `floor_sum` adds up for a whole run of x values at once.
+//
+// That is O(n log n) to sort the nodes and build the walks, and O(n log T) to add
+// up the runs, however large the region.
+fn count_separable_area(x_axis: &AxisDistances, y_axis: &AxisDistances, max_distance: i128) -> u64 {
+    let median_x = x_axis.median();
+    let median_y = y_axis.median();
+    let x_walks = [x_axis.walk(median_x, 1), x_axis.walk(median_x - 1, -1)];
+    let y_walks = [y_axis.walk(median_y, 1), y_axis.walk(median_y - 1, -1)];
+
+    let mut count = 0;
+    for x_walk in x_walks.iter() {
+        for y_walk in y_walks.iter() {
+            count += count_quadrant(x_walk, y_walk, max_distance);
+        }
+    }
+
+    count as u64
+}
+
+// Counts the steps (i, j) >= (0, 0) along two walks with totals under the maximum.
+fn count_quadrant(x_walk: &[Piece], y_walk: &[Piece], max_distance: i128) -> i128 {
+    let mut count = 0;
+    let mut y_piece = y_walk.len() - 1;
+
+    for (index, x_piece) in x_walk.iter().enumerate() {
+        let piece_end = match x_walk.get(index + 1) {
+            Some(next) => i128::from(next.offset) - 1,
+            None => i128::MAX,
+        };
+        let x_total =
+            |step: i128| x_piece.total + x_piece.slope * (step - i128::from(x_piece.offset));
+
+        let mut step = i128::from(x_piece.offset);
+        while step <= piece_end {
+            // The y walk piece holding the last y with a total under what's left.
+            let remaining = max_distance - x_total(step);
+            while y_walk[y_piece].total >= remaining {
+                if y_piece == 0 {
+                    return count;
+                }
+                y_piece -= 1;
+            }
+
+            // This piece has a positive slope, since a flat piece ending below
+            // `remaining` would leave the next piece's start below it too. The y
+            // piece stays the same for as long as its start is under what's left.
+            let piece = &y_walk[y_piece];
+            let last_step = if x_piece.slope == 0 {
+                piece_end
+            } else {
+                piece_end.min(
+                    i128::from(x_piece.offset)
+                        + (max_distance - x_piece.total - piece.total - 1) / x_piece.slope,
+                )
+            };
+
+            // For each x step, the y steps up to the piece's start, plus
+            // ceil((remaining - piece.total) / piece.slope) more. Summed in reverse, so
+            // the numerator grows with each step.
+            let steps = last_step - step + 1;
+            let last_remaining = max_distance - x_total(last_step);
+            count += i128::from(piece.offset) * steps
+                + floor_sum(
+                    steps,
+                    piece.slope,
+                    x_piece.slope,
+                    last_remaining - piece.total + piece.slope - 1,
+                );
+
+            step = last_step + 1;
+        }
+    }
+
+    count
+}
+
+// The first value from `low` to `high` where `is_past` holds, by binary search, given
+// that it holds at `high` and from any value where it holds onwards.
+fn first_where<F>(mut low: i64, mut high: i64, is_past: F) -> i64
+where
+    F: Fn(i64) -> bool,
+{
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if is_past(mid) {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+
+    low
+}
+
+// The sum of floor((a * i + b) / m) for i from 0 to n - 1, for non-negative a and b
+// and positive m, in O(log m) by repeatedly swapping the roles of a and m.
+fn floor_sum(mut n: i128, mut m: i128, mut a: i128, mut b: i128) -> i128 {
+    let mut sum = 0;
+    loop {
+        if a >= m {
+            sum += n * (n - 1) / 2 * (a / m);
+            a %= m;
+        }
+        if b >= m {
+            sum += n * (b / m);
+            b %= m;
+        }
+
+        let y_max = a * n + b;
+        if y_max < m {
+            return sum;
+        }
+        n = y_max / m;
+        b = y_max % m;
+        std::mem::swap(&mut m, &mut a);
+    }
 }
 
-fn calculate_max_possible_area(node_list: &NodeList, max_distance: i32) -> Area {
+// A point `gap` beyond the nodes' bounding box along one axis is at least
+// `distance((0, 0), (gap, 0))` from every node, so it can only be in the region if
+// `node_count` times that is under the maximum. The margin on each axis is the
+// largest such gap. With a large maximum, the binary search tries gaps whose
+// squared Euclidean distance is near 1e18, so the product saturates rather than
+// overflowing, which still counts as over the maximum.
+pub fn calculate_max_possible_area(
+    metric: &dyn Metric,
+    node_list: &NodeList,
+    max_distance: i64,
+) -> Area {
     let bounding_area = Area::from_node_list(node_list);
-    let node_count = node_list.len();
+    let node_count = node_list.len() as i64;
+    let origin = Point::new(0, 0);
+
+    let x_margin = largest_gap(max_distance, |gap| {
+        node_count.saturating_mul(metric.distance(&origin, &Point::new(gap, 0)))
+    });
+    let y_margin = largest_gap(max_distance, |gap| {
+        node_count.saturating_mul(metric.distance(&origin, &Point::new(0, gap)))
+    });
+
+    Area::new(
+        bounding_area.min_x().saturating_sub(x_margin),
+        bounding_area.min_y().saturating_sub(y_margin),
+        bounding_area.max_x().saturating_add(x_margin),
+        bounding_area.max_y().saturating_add(y_margin),
+    )
+}
 
-    let min_x =
-        bounding_area.min_x() - ((max_distance as f32 / node_count as f32)).floor() as i32;
-    let min_y =
-        bounding_area.min_y() - ((max_distance as f32 / node_count as f32)).floor() as i32;
-    let max_x =
-        bounding_area.max_x() + ((max_distance as f32 / node_count as f32)).floor() as i32;
-    let max_y =
-        bounding_area.max_y() + ((max_distance as f32 / node_count as f32)).floor() as i32;
+// The largest gap with a total distance under the maximum, by binary search. Every
+// metric's distance grows with the gap and is at least the gap, so the answer is
+// below `max_distance`.
+fn largest_gap<F>(max_distance: i64, total_distance: F) -> i32
+where
+    F: Fn(i32) -> i64,
+{
+    let (mut low, mut high) = (0, max_distance.clamp(0, i64::from(i32::MAX)) as i32);
+    while low < high {
+        let mid = ((i64::from(low) + i64::from(high) + 1) / 2) as i32;
+        if total_distance(mid) < max_distance {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
 
-    Area::new(min_x, min_y, max_x, max_y)
+    low
 }
 
-fn find_area(grid: &Box<Grid>, max_distance: i32) -> i32 {
-   grid.iter().filter(|grid_point| grid_point.total_distance() < max_distance).count() as i32
-}
\ No newline at end of file
+fn count_cells(
+    metric: &dyn Metric,
+    node_list: &NodeList,
+    total_area: &Area,
+    max_distance: i64,
+) -> u64 {
+    let mut count = 0;
+    for curr_x in total_area.min_x()..=total_area.max_x() {
+        for curr_y in total_area.min_y()..=total_area.max_y() {
+            let curr_point = Point::new(curr_x, curr_y);
+            let total_distance: i64 = node_list
+                .iter()
+                .map(|node| metric.distance(&curr_point, node))
+                .sum();
+            if total_distance < max_distance {
+                count += 1;
+            }
+        }
+    }
+
+    count
+}
+
+#[cfg(test)]
+mod test_find_area {
+    use super::{calculate_max_possible_area, count_cells, find_area, find_manhattan_area};
+    use crate::helpers::{get_node_list, manhattan_distance, Area, NodeList, Point, EXAMPLE};
+    use crate::metric::{Chebyshev, EuclideanSquared, Manhattan, Metric, WeightedManhattan};
+
+    #[test]
+    fn puzzle_example() {
+        let node_list = get_node_list(EXAMPLE).unwrap();
+        assert_eq!(find_area(&Manhattan, &node_list, 32), 16);
+    }
+
+    #[test]
+    fn separable_matches_every_cell() {
+        let inputs = [
+            EXAMPLE,
+            "0, 0",
+            "5, -3\n-7, 2\n0, 0\n12, 9\n-7, 2\n3, 14",
+            "3, 3\n3, 3\n-4, 10",
+            "0, 0\n1, 0\n2, 0\n3, 0",
+        ];
+        let metrics: Vec<Box<dyn Metric>> = vec![
+            Box::new(Manhattan),
+            Box::new(WeightedManhattan::new(2, 5).unwrap()),
+        ];
+
+        for input in inputs.iter() {
+            let node_list = get_node_list(input).unwrap();
+            for metric in metrics.iter() {
+                for max_distance in [0, 1, 2, 7, 32, 100, 150].iter() {
+                    let everything = Area::new(-200, -200, 210, 210);
+                    assert_eq!(
+                        find_area(metric.as_ref(), &node_list, *max_distance),
+                        count_cells(metric.as_ref(), &node_list, &everything, *max_distance),
+                        "{} {:?} under {}",
+                        metric.name(),
+                        input,
+                        max_distance
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn search_area_covers_region() {
+        let node_list = get_node_list(EXAMPLE).unwrap();
+        let metrics: Vec<Box<dyn Metric>> = vec![
+            Box::new(Manhattan),
+            Box::new(WeightedManhattan::new(2, 5).unwrap()),
+            Box::new(Chebyshev),
+            Box::new(EuclideanSquared),
+        ];
+
+        for metric in metrics.iter() {
+            let total_area = calculate_max_possible_area(metric.as_ref(), &node_list, 200);
+            let everything = Area::new(-100, -100, 110, 110);
+            assert_eq!(
+                count_cells(metric.as_ref(), &node_list, &total_area, 200),
+                count_cells(metric.as_ref(), &node_list, &everything, 200),
+                "{}",
+                metric.name()
+            );
+        }
+    }
+
+    #[test]
+    fn large_euclidean_threshold() {
+        // Ten nodes at the origin are 10 * gap^2 from a point `gap` away along an
+        // axis, which is under 5e9 up to a gap of 22360.
+        let node_list = get_node_list(&["0, 0"; 10].join("\n")).unwrap();
+        assert_eq!(
+            calculate_max_possible_area(&EuclideanSquared, &node_list, 5_000_000_000),
+            Area::new(-22360, -22360, 22360, 22360)
+        );
+    }
+
+    #[test]
+    fn large_threshold() {
+        // A single node's region is a diamond with 2r^2 - 2r + 1 points for radius r.
+        let node_list = get_node_list("0, 0").unwrap();
+        let radius: u64 = 3_000_000_000;
+        assert_eq!(
+            find_area(&Manhattan, &node_list, radius as i64),
+            2 * radius * radius - 2 * radius + 1
+        );
+    }
+
+    #[test]
+    fn large_threshold_in_three_dimensions() {
+        // A single node's region is an octahedron with (2r + 1)(2r^2 + 2r + 3) / 3
+        // points within distance r.
+        let node_list: NodeList<3> = get_node_list("0, 0, 0").unwrap();
+        let radius: u64 = 99_999;
+        assert_eq!(
+            find_manhattan_area(&node_list, radius as i64 + 1),
+            Ok((2 * radius + 1) * (2 * radius * radius + 2 * radius + 3) / 3)
+        );
+
+        assert!(find_manhattan_area(&node_list, 1_000_000_000_000).is_err());
+        let node_list: NodeList<4> = get_node_list("0, 0, 0, 0").unwrap();
+        assert!(find_manhattan_area(&node_list, 100_000).is_err());
+    }
+
+    #[test]
+    fn any_dimensions() {
+        let node_list: NodeList = get_node_list(EXAMPLE).unwrap();
+        assert_eq!(find_manhattan_area(&node_list, 32), Ok(16));
+
+        let node_list: NodeList<1> = get_node_list("0\n3\n10").unwrap();
+        assert_eq!(find_manhattan_area(&node_list, 15), Ok(8));
+
+        let node_list: NodeList<3> =
+            get_node_list("0, 0, 0\n3, -2, 5\n-1, 4, 2\n3, -2, 5").unwrap();
+        for max_distance in [0, 1, 17, 30, 45].iter() {
+            let mut count = 0;
+            for x in -20..=20 {
+                for y in -20..=20 {
+                    for z in -20..=20 {
+                        let point = Point { coords: [x, y, z] };
+                        let total: i32 = node_list
+                            .iter()
+                            .map(|node| manhattan_distance(&point, node))
+                            .sum();
+                        if i64::from(total) < *max_distance {
+                            count += 1;
+                        }
+                    }
+                }
+            }
+            assert_eq!(find_manhattan_area(&node_list, *max_distance), Ok(count));
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use crate::parser::StepName;
+use std::collections::HashMap;
+
+// When a step can be started with as many workers as needed: no earlier than
+// `earliest_start`, as its prior steps won't all be finished before then, and no
+// later than `latest_start` without delaying the whole job.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct StepTiming {
+    pub earliest_start: u32,
+    pub latest_start: u32,
+    pub duration: u32,
+}
+
+impl StepTiming {
+    pub fn earliest_finish(&self) -> u32 {
+        self.earliest_start + self.duration
+    }
+
+    // How long the step can be put off without delaying the whole job.
+    pub fn slack(&self) -> u32 {
+        self.latest_start - self.earliest_start
+    }
+}
+
+// The timings of every step with unlimited workers, and the critical path: the
+// longest chain of dependent steps, which the job can't be finished any faster than.
+#[derive(PartialEq, Eq, Debug)]
+pub struct CriticalPath {
+    timings: HashMap<StepName, StepTiming>,
+    path: Vec<StepName>,
+    length: u32,
+    total_work: u32,
+}
+
+impl CriticalPath {
+    pub fn new(timings: HashMap<StepName, StepTiming>, path: Vec<StepName>) -> CriticalPath {
+        let length = timings
+            .values()
+            .map(StepTiming::earliest_finish)
+            .max()
+            .unwrap_or(0);
+        let total_work = timings.values().map(|timing| timing.duration).sum();
+
+        CriticalPath {
+            timings,
+            path,
+            length,
+            total_work,
+        }
+    }
+
+    pub fn path(&self) -> &[StepName] {
+        &self.path
+    }
+
+    // The shortest possible time to do every step, however many workers there are.
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+
+    // Every step with its timing, in the order the steps can first be started.
+    pub fn timings(&self) -> Vec<(&StepName, &StepTiming)> {
+        let mut timings: Vec<(&StepName, &StepTiming)> = self.timings.iter().collect();
+        timings.sort_unstable_by_key(|(step, timing)| (timing.earliest_start, *step));
+        timings
+    }
+
+    // No schedule for this many workers can finish sooner than this: not before the
+    // critical path is done, and not before the workers have got through all of the
+    // work between them.
+    pub fn makespan_lower_bound(&self, workers: usize) -> u32 {
+        let workers = workers.max(1) as u32;
+        let shared_work = self.total_work.div_ceil(workers);
+        self.length.max(shared_work)
+    }
+}
+
+#[cfg(test)]
+mod test_critical_path {
+    use crate::parser::{format_order, Graph, EXAMPLE};
+    use crate::policy::Alphabetical;
+    use crate::schedule::Schedule;
+
+    #[test]
+    fn puzzle_example() {
+        let critical_path = Graph::new(EXAMPLE)
+            .critical_path(&Schedule::new(2, 0).unwrap().with_letter_offsets())
+            .unwrap();
+        assert_eq!(format_order(critical_path.path()), "CFE");
+        assert_eq!(critical_path.length(), 3 + 6 + 5);
+
+        let slack: Vec<(String, u32, u32, u32)> = critical_path
+            .timings()
+            .iter()
+            .map(|(step, timing)| {
+                (
+                    step.to_string(),
+                    timing.earliest_start,
+                    timing.latest_start,
+                    timing.slack(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            slack,
+            vec![
+                ("C".to_owned(), 0, 0, 0),
+                ("A".to_owned(), 3, 4, 1),
+                ("F".to_owned(), 3, 3, 0),
+                ("B".to_owned(), 4, 7, 3),
+                ("D".to_owned(), 4, 5, 1),
+                ("E".to_owned(), 9, 9, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn makespan_lower_bound() {
+        let graph = Graph::new(EXAMPLE);
+        let critical_path = graph
+            .critical_path(&Schedule::new(2, 0).unwrap().with_letter_offsets())
+            .unwrap();
+
+        // One worker has to do all 21 seconds of work; two are held up by the
+        // 14 second critical path, which the alphabetical order misses by a second.
+        assert_eq!(critical_path.makespan_lower_bound(1), 21);
+        assert_eq!(critical_path.makespan_lower_bound(2), 14);
+        assert_eq!(critical_path.makespan_lower_bound(100), 14);
+
+        let timeline = graph
+            .find_in_order_with_durations(
+                &Schedule::new(2, 0).unwrap().with_letter_offsets(),
+                &Alphabetical,
+            )
+            .unwrap();
+        assert!(timeline.total_time() >= critical_path.makespan_lower_bound(2));
+    }
+
+    #[test]
+    fn independent_steps() {
+        let critical_path = Graph::new(
+            "Step A must be finished before step B can begin.
+Step X must be finished before step Y can begin.",
+        )
+        .critical_path(&Schedule::new(5, 10).unwrap().with_letter_offsets())
+        .unwrap();
+        assert_eq!(format_order(critical_path.path()), "XY");
+        assert_eq!(critical_path.length(), 34 + 35);
+        let (_, b) = critical_path
+            .timings()
+            .into_iter()
+            .find(|(step, _)| step.as_str() == "B")
+            .unwrap();
+        assert_eq!(b.slack(), (34 + 35 - 12) - 11);
+    }
+
+    #[test]
+    fn cycles_have_no_critical_path() {
+        let graph = Graph::new(
+            "Step A must be finished before step B can begin.
+Step B must be finished before step A can begin.",
+        );
+        assert!(graph.critical_path(&Schedule::default()).is_err());
+    }
+}
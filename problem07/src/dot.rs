@@ -0,0 +1,462 @@
+use crate::parser::{Graph, StepName};
+use crate::schedule::Schedule;
+use std::collections::HashMap;
+
+// Writes the graph in Graphviz's DOT language, with an edge from each step to the
+// steps that depend on it. Each step is labelled with its duration and, given the
+// order the steps are done in, its position in that order. Both are also written
+// as `duration` and `order` attributes, which `from_dot` reads durations back from.
+pub fn to_dot(graph: &Graph, schedule: &Schedule, order: Option<&[StepName]>) -> String {
+    let positions: HashMap<&StepName, usize> = order
+        .unwrap_or(&[])
+        .iter()
+        .enumerate()
+        .map(|(index, step_name)| (step_name, index + 1))
+        .collect();
+
+    let mut lines = vec![
+        "digraph steps {".to_owned(),
+        "    node [shape=box];".to_owned(),
+    ];
+    for step_name in graph.step_names() {
+        let duration = schedule.step_duration(step_name);
+        lines.push(match positions.get(step_name) {
+            Some(position) => format!(
+                r#"    "{0}" [label="{0}\n{1}s\n#{2}", duration={1}, order={2}];"#,
+                step_name, duration, position
+            ),
+            None => format!(
+                r#"    "{0}" [label="{0}\n{1}s", duration={1}];"#,
+                step_name, duration
+            ),
+        });
+    }
+    for (depends_on, current_step) in graph.edges() {
+        lines.push(format!(r#"    "{}" -> "{}";"#, depends_on, current_step));
+    }
+    lines.push("}".to_owned());
+
+    lines.join("\n")
+}
+
+// A graph read from DOT, along with any durations given by its nodes' `duration`
+// attributes.
+pub struct DotGraph {
+    pub graph: Graph,
+    pub durations: Vec<(StepName, u32)>,
+}
+
+// Reads a graph from a subset of the DOT language: a single `digraph` made of node
+// statements (`A [duration=5]`), edge statements (`A -> B -> C`), and graph, node
+// and edge attribute statements, which are ignored. Comments and quoted IDs are
+// supported, but subgraphs, ports and undirected graphs aren't. As in DOT, `#`
+// only starts a comment as the first character of a line. Unquoted IDs can contain
+// `-` to match step names, as long as it doesn't start `->` or `--`.
+pub fn from_dot(input: &str) -> Result<DotGraph, String> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        position: 0,
+    };
+    let mut dot_graph = DotGraph {
+        graph: Graph::empty(),
+        durations: vec![],
+    };
+
+    if parser.next_is_keyword("strict") {
+        parser.advance();
+    }
+    if parser.next_is_keyword("graph") {
+        return Err(parser.error("only directed graphs (digraph) are supported"));
+    }
+    if !parser.next_is_keyword("digraph") {
+        return Err(parser.error("expected 'digraph'"));
+    }
+    parser.advance();
+    if let Some(Token::Id(_)) | Some(Token::QuotedId(_)) = parser.peek() {
+        parser.advance();
+    }
+    parser.expect(&Token::Symbol('{'))?;
+
+    loop {
+        match parser.peek() {
+            None => return Err(parser.error("expected '}'")),
+            Some(Token::Symbol('}')) => {
+                parser.advance();
+                break;
+            }
+            Some(Token::Symbol(';')) => parser.advance(),
+            Some(Token::Symbol('{')) => return Err(parser.error("subgraphs aren't supported")),
+            Some(Token::Id(_)) if parser.next_is_keyword("subgraph") => {
+                return Err(parser.error("subgraphs aren't supported"))
+            }
+            Some(Token::Id(_))
+                if parser.next_is_keyword("graph")
+                    || parser.next_is_keyword("node")
+                    || parser.next_is_keyword("edge") =>
+            {
+                parser.advance();
+                parser.attributes()?;
+            }
+            Some(Token::Id(_)) | Some(Token::QuotedId(_)) => parser.statement(&mut dot_graph)?,
+            Some(_) => return Err(parser.error("expected a statement")),
+        }
+    }
+
+    if parser.peek().is_some() {
+        return Err(parser.error("expected the end of the graph"));
+    }
+
+    Ok(dot_graph)
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+enum Token {
+    // Quoted IDs are kept apart so that `"node"` isn't taken for a keyword.
+    Id(String),
+    QuotedId(String),
+    Arrow,
+    Symbol(char),
+}
+
+// Splits the input into tokens, each with the line it's on.
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, String> {
+    let mut tokens = vec![];
+    let mut chars = input.chars().peekable();
+    let mut line = 1;
+    let mut line_start = true;
+
+    while let Some(c) = chars.next() {
+        let at_line_start = line_start;
+        line_start = c == '\n';
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {}
+            '#' if at_line_start => {
+                while chars.peek().is_some_and(|c| *c != '\n') {
+                    chars.next();
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|c| *c != '\n') {
+                    chars.next();
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                loop {
+                    match chars.next() {
+                        None => return Err(format!("Line {}: unterminated comment", line)),
+                        Some('/') if previous == '*' => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            previous = c;
+                        }
+                    }
+                }
+            }
+            '-' if chars.peek() == Some(&'>') => {
+                chars.next();
+                tokens.push((Token::Arrow, line));
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                return Err(format!(
+                    "Line {}: undirected edges (--) aren't supported",
+                    line
+                ));
+            }
+            '{' | '}' | '[' | ']' | '=' | ';' | ',' | ':' => tokens.push((Token::Symbol(c), line)),
+            '"' => {
+                let start_line = line;
+                let mut id = String::new();
+                loop {
+                    match chars.next() {
+                        None => return Err(format!("Line {}: unterminated string", start_line)),
+                        Some('"') => break,
+                        Some('\\') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            id.push('"');
+                        }
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            id.push(c);
+                        }
+                    }
+                }
+                tokens.push((Token::QuotedId(id), start_line));
+            }
+            c if is_id_char(c) => {
+                let mut id = c.to_string();
+                while let Some(c) = chars.peek().copied().filter(|c| is_id_char(*c)) {
+                    if c == '-' {
+                        let mut ahead = chars.clone();
+                        ahead.next();
+                        if let Some('>') | Some('-') = ahead.peek() {
+                            break;
+                        }
+                    }
+                    chars.next();
+                    id.push(c);
+                }
+                tokens.push((Token::Id(id), line));
+            }
+            c => return Err(format!("Line {}: unexpected character '{}'", line, c)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn is_id_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.' || c == '-'
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn advance(&mut self) {
+        self.position += 1;
+    }
+
+    fn next_is_keyword(&self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Id(id)) => id.eq_ignore_ascii_case(keyword),
+            _ => false,
+        }
+    }
+
+    fn error(&self, message: &str) -> String {
+        match self.tokens.get(self.position) {
+            Some((_, line)) => format!("Line {}: {}", line, message),
+            None => format!("End of input: {}", message),
+        }
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<(), String> {
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("expected {:?}", expected)));
+        }
+        self.advance();
+        Ok(())
+    }
+
+    fn id(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some(Token::Id(id)) | Some(Token::QuotedId(id)) => {
+                let id = id.clone();
+                self.advance();
+                Ok(id)
+            }
+            _ => Err(self.error("expected an ID")),
+        }
+    }
+
+    fn step_name(&mut self) -> Result<StepName, String> {
+        if let Some(Token::Symbol('{')) = self.peek() {
+            return Err(self.error("subgraphs aren't supported"));
+        }
+        let id = self.id()?;
+        if let Some(Token::Symbol(':')) = self.peek() {
+            return Err(self.error("ports aren't supported"));
+        }
+        id.parse::<StepName>()
+            .map_err(|e| self.error(&e.to_string()))
+    }
+
+    // Reads any number of `[name=value, ...]` lists.
+    fn attributes(&mut self) -> Result<Vec<(String, String)>, String> {
+        let mut attributes = vec![];
+        while let Some(Token::Symbol('[')) = self.peek() {
+            self.advance();
+            loop {
+                match self.peek() {
+                    Some(Token::Symbol(']')) => {
+                        self.advance();
+                        break;
+                    }
+                    Some(Token::Symbol(',')) | Some(Token::Symbol(';')) => self.advance(),
+                    _ => {
+                        let name = self.id()?;
+                        self.expect(&Token::Symbol('='))?;
+                        let value = self.id()?;
+                        attributes.push((name, value));
+                    }
+                }
+            }
+        }
+
+        Ok(attributes)
+    }
+
+    // A graph attribute (`rankdir=LR`), a node statement or an edge statement.
+    fn statement(&mut self, dot_graph: &mut DotGraph) -> Result<(), String> {
+        if let Some(Token::Symbol('=')) = self.tokens.get(self.position + 1).map(|(t, _)| t) {
+            self.advance();
+            self.advance();
+            self.id()?;
+            return Ok(());
+        }
+
+        let mut step_names = vec![self.step_name()?];
+        while let Some(Token::Arrow) = self.peek() {
+            self.advance();
+            step_names.push(self.step_name()?);
+        }
+        let line_error = self.error("invalid duration");
+        let attributes = self.attributes()?;
+
+        if let [step_name] = &step_names[..] {
+            dot_graph.graph.add_step(step_name);
+            for (name, value) in attributes {
+                if name == "duration" {
+                    let duration = value
+                        .parse::<u32>()
+                        .map_err(|e| format!("{} '{}': {}", line_error, value, e))?;
+                    dot_graph.durations.push((step_name.clone(), duration));
+                }
+            }
+        } else {
+            for pair in step_names.windows(2) {
+                dot_graph.graph.add_edge(&pair[0], &pair[1]);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_dot {
+    use super::{from_dot, to_dot};
+    use crate::parser::{format_order, Graph, EXAMPLE};
+    use crate::schedule::Schedule;
+
+    #[test]
+    fn export() {
+        let graph = Graph::new("Step C must be finished before step A can begin.");
+        let order = graph.find_in_order().unwrap();
+        assert_eq!(
+            to_dot(
+                &graph,
+                &Schedule::default().with_letter_offsets(),
+                Some(&order)
+            ),
+            r#"digraph steps {
+    node [shape=box];
+    "A" [label="A\n61s\n#2", duration=61, order=2];
+    "C" [label="C\n63s\n#1", duration=63, order=1];
+    "C" -> "A";
+}"#
+        );
+        assert!(to_dot(
+            &graph,
+            &Schedule::new(1, 0).unwrap().with_letter_offsets(),
+            None
+        )
+        .contains(r#""A" [label="A\n1s", duration=1];"#));
+    }
+
+    #[test]
+    fn round_trip() {
+        let graph = Graph::new(EXAMPLE);
+        let schedule = Schedule::new(2, 0).unwrap().with_letter_offsets();
+        let dot_graph = from_dot(&to_dot(&graph, &schedule, None)).unwrap();
+
+        assert_eq!(dot_graph.graph.edges(), graph.edges());
+        assert_eq!(dot_graph.durations.len(), 6);
+        assert!(dot_graph
+            .durations
+            .iter()
+            .all(|(step, duration)| schedule.step_duration(step) == *duration));
+    }
+
+    #[test]
+    fn hyphenated_names_round_trip() {
+        let graph = Graph::new(
+            "Step fetch must be finished before step compile-core can begin.
+Step compile-core must be finished before step link-all can begin.
+Step x-1 must be finished before step link-all can begin.",
+        );
+        let schedule = Schedule::default();
+
+        let dot_graph = from_dot(&to_dot(&graph, &schedule, None)).unwrap();
+        assert_eq!(dot_graph.graph.edges(), graph.edges());
+
+        // The same edges, written without quotes and with or without spaces.
+        let unquoted: Vec<String> = graph
+            .edges()
+            .iter()
+            .enumerate()
+            .map(|(index, (depends_on, current_step))| match index % 2 {
+                0 => format!("{} -> {}", depends_on, current_step),
+                _ => format!("{}->{}", depends_on, current_step),
+            })
+            .collect();
+        let dot_graph = from_dot(&format!("digraph {{\n{}\n}}", unquoted.join("\n"))).unwrap();
+        assert_eq!(dot_graph.graph.edges(), graph.edges());
+        assert_eq!(
+            format_order(&dot_graph.graph.find_in_order().unwrap()),
+            "fetch compile-core x-1 link-all"
+        );
+    }
+
+    #[test]
+    fn sketched_graph() {
+        let dot_graph = from_dot(
+            r#"/* a build */
+strict digraph "build" {
+    rankdir = LR
+    node [shape=ellipse, color="blue"]
+    fetch -> "compile-core" -> link -> ship [color=red]
+    compile_tests -> link
+    // documentation can be written at any time
+    docs; fetch [duration=5]
+# lines starting with a hash are ignored too
+}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            format_order(&dot_graph.graph.find_in_order().unwrap()),
+            "compile_tests docs fetch compile-core link ship"
+        );
+        assert_eq!(dot_graph.durations, vec![("fetch".parse().unwrap(), 5)]);
+    }
+
+    #[test]
+    fn unsupported_dot() {
+        assert_eq!(
+            from_dot("graph { A -- B }").err().unwrap(),
+            "Line 1: undirected edges (--) aren't supported"
+        );
+        assert_eq!(
+            from_dot("graph { A }").err().unwrap(),
+            "Line 1: only directed graphs (digraph) are supported"
+        );
+        assert_eq!(
+            from_dot("digraph {\n  A -> { B C }\n}").err().unwrap(),
+            "Line 2: subgraphs aren't supported"
+        );
+        assert!(from_dot("digraph { A -> B").is_err());
+        assert!(from_dot("digraph { \"A?\" -> B }").is_err());
+        assert!(from_dot("digraph { A [duration=soon] }").is_err());
+        assert!(from_dot("digraph { A } B").is_err());
+        assert_eq!(
+            from_dot("digraph {\n  A -> B # not a comment\n}")
+                .err()
+                .unwrap(),
+            "Line 2: unexpected character '#'"
+        );
+    }
+}
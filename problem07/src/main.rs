@@ -1,22 +1,198 @@
+use crate::policy::{compare_policies, get_policy, Alphabetical, SchedulingPolicy};
+use crate::schedule::Schedule;
 use std::fs;
 extern crate lazy_static;
 
+mod critical_path;
+mod dot;
 mod parser;
+mod policy;
+mod schedule;
+mod timeline;
 
 fn main() -> Result<(), String> {
-    let input = fs::read_to_string("input/data.txt").map_err(|e| e.to_string())?;
-    part1(&input);
-    part2(&input);
+    let graph_path = get_arg_value("--graph")?;
+    let (graph, dot_durations) = match &graph_path {
+        Some(path) => {
+            let dot_graph = dot::from_dot(&fs::read_to_string(path).map_err(|e| e.to_string())?)?;
+            (dot_graph.graph, dot_graph.durations)
+        }
+        None => {
+            let input = fs::read_to_string("input/data.txt").map_err(|e| e.to_string())?;
+            (parser::Graph::new(&input), vec![])
+        }
+    };
+    if let Err(problems) = graph.validate() {
+        let problems: Vec<String> = problems.iter().map(|problem| problem.to_string()).collect();
+        return Err(problems.join("\n"));
+    }
+
+    let default = Schedule::default();
+    let workers = match get_arg_value("--workers")? {
+        Some(workers) => workers.parse().map_err(|e| format!("--workers: {}", e))?,
+        None => default.workers(),
+    };
+    let base_duration = match get_arg_value("--base-duration")? {
+        Some(base) => base
+            .parse()
+            .map_err(|e| format!("--base-duration: {}", e))?,
+        None => default.base_duration(),
+    };
+    let mut schedule = Schedule::new(workers, base_duration)?;
+    // The puzzle's steps take extra time for their letter, other graphs' don't
+    // unless asked to.
+    let puzzle_durations = (graph_path.is_none()
+        || std::env::args().any(|arg| arg == "--puzzle-durations"))
+        && !std::env::args().any(|arg| arg == "--flat-durations");
+    if puzzle_durations {
+        schedule = schedule.with_letter_offsets();
+    }
+    for (step, duration) in dot_durations {
+        schedule = schedule.with_duration(step, duration);
+    }
+    if let Some(path) = get_arg_value("--durations")? {
+        schedule =
+            schedule.with_durations_from(&fs::read_to_string(path).map_err(|e| e.to_string())?)?;
+    }
+
+    let policy: Box<dyn SchedulingPolicy> = match get_arg_value("--policy")? {
+        Some(name) => get_policy(&name)?,
+        None => Box::new(Alphabetical),
+    };
+
+    part1(&graph)?;
+    part2(&graph, &schedule, policy.as_ref())?;
+
+    if std::env::args().any(|arg| arg == "--compare-policies") {
+        let lower_bound = graph
+            .critical_path(&schedule)
+            .map_err(|e| e.to_string())?
+            .makespan_lower_bound(schedule.workers());
+        println!("Policy              Time   Over fastest possible");
+        for (name, time) in compare_policies(&graph, &schedule).map_err(|e| e.to_string())? {
+            println!("{:<16} {:>7} {:>23}", name, time, time - lower_bound);
+        }
+    }
+
+    if let Some(path) = get_arg_value("--dot")? {
+        let order = match std::env::args().any(|arg| arg == "--dot-order") {
+            true => Some(graph.find_in_order().map_err(|e| e.to_string())?),
+            false => None,
+        };
+        fs::write(path, dot::to_dot(&graph, &schedule, order.as_deref()))
+            .map_err(|e| e.to_string())?;
+    }
     Ok(())
 }
 
-fn part1(input: &str) {
-    println!("Part 1 = {}", parser::Graph::new(input).find_in_order());
+// Reads the value following a command line flag. Supported flags are:
+//   --workers <n>          how many workers share the steps in part 2 (5)
+//   --base-duration <n>    seconds every step takes (60)
+//   --graph <file>         a DOT digraph to read the steps from, instead of the input
+//   --durations <file>     per-step durations, one `<step> <seconds>` per line, which
+//                          replace any durations given in the DOT graph
+//   --dot <file>           where to write the steps as a DOT digraph
+//   --svg <file>           where to write a Gantt chart of part 2's schedule
+//   --policy <name>        which ready steps part 2 starts first: alphabetical (the
+//                          default), longest-duration, most-dependents or critical-path
+// and `--puzzle-durations` makes single-letter steps from a DOT graph take extra time
+// for their letter as the puzzle's steps do, `--flat-durations` stops them doing so,
+// `--dot-order` numbers the steps in the DOT output by their part 1 position,
+// `--table` prints part 2's schedule second by second, `--slack` prints when each
+// step could start with unlimited workers and `--compare-policies` prints how long
+// part 2 takes with each policy.
+fn get_arg_value(flag: &str) -> Result<Option<String>, String> {
+    let mut args = std::env::args().skip_while(|arg| arg != flag);
+    match args.next() {
+        None => Ok(None),
+        Some(_) => args
+            .next()
+            .map(Some)
+            .ok_or_else(|| format!("{} requires a value", flag)),
+    }
 }
 
-fn part2(input: &str) {
+fn part1(graph: &parser::Graph) -> Result<(), String> {
+    let order = graph.find_in_order().map_err(|e| e.to_string())?;
+    println!("Part 1 = {}", parser::format_order(&order));
+    Ok(())
+}
+
+fn part2(
+    graph: &parser::Graph,
+    schedule: &Schedule,
+    policy: &dyn SchedulingPolicy,
+) -> Result<(), String> {
+    let timeline = graph
+        .find_in_order_with_durations(schedule, policy)
+        .map_err(|e| e.to_string())?;
+    let policy_name = match policy.name().as_str() {
+        "alphabetical" => String::new(),
+        name => format!(" ({})", name),
+    };
     println!(
-        "Part 2 = {:?}",
-        parser::Graph::new(input).find_in_order_with_durations()
+        "Part 2{} = ({:?}, {})",
+        policy_name,
+        parser::format_order(timeline.order()),
+        timeline.total_time()
     );
+
+    let critical_path = graph.critical_path(schedule).map_err(|e| e.to_string())?;
+    println!(
+        "Critical path = {} ({}), fastest possible with {} workers = {}",
+        parser::format_order(critical_path.path()),
+        critical_path.length(),
+        schedule.workers(),
+        critical_path.makespan_lower_bound(schedule.workers())
+    );
+
+    if std::env::args().any(|arg| arg == "--slack") {
+        println!("Step   Earliest start   Latest start   Slack");
+        for (step, timing) in critical_path.timings() {
+            println!(
+                "{:<6} {:>14} {:>14} {:>7}",
+                step,
+                timing.earliest_start,
+                timing.latest_start,
+                timing.slack()
+            );
+        }
+    }
+    if std::env::args().any(|arg| arg == "--table") {
+        println!("{}", timeline.to_table());
+        for worker in 0..schedule.workers() {
+            println!(
+                "Worker {} idle for {}",
+                worker + 1,
+                timeline.idle_time(worker)
+            );
+        }
+    }
+    if let Some(path) = get_arg_value("--svg")? {
+        fs::write(path, timeline.to_svg()).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_puzzle {
+    use crate::parser::{format_order, Graph};
+    use crate::policy::Alphabetical;
+    use crate::schedule::Schedule;
+    use std::fs;
+
+    #[test]
+    fn puzzle_answers() {
+        let graph = Graph::new(&fs::read_to_string("input/data.txt").unwrap());
+        assert_eq!(
+            format_order(&graph.find_in_order().unwrap()),
+            "BCEFLDMQTXHZGKIASVJYORPUWN"
+        );
+
+        let timeline = graph
+            .find_in_order_with_durations(&Schedule::default().with_letter_offsets(), &Alphabetical)
+            .unwrap();
+        assert_eq!(format_order(timeline.order()), "BFLTCDMZEGVQXKHIAJSYORPUWN");
+        assert_eq!(timeline.total_time(), 987);
+    }
 }
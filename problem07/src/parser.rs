@@ -1,15 +1,126 @@
+use crate::critical_path::{CriticalPath, StepTiming};
+use crate::policy::SchedulingPolicy;
+use crate::schedule::Schedule;
+use crate::timeline::Timeline;
 use lazy_static::*;
 use regex::Regex;
-use std::collections::{HashMap, HashSet};
-use std::iter::FromIterator;
+use std::borrow::Borrow;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
 use std::str::FromStr;
 
-type StepName = char;
+// A step's name, e.g. `A` or `compile-core`. Names are compared as strings, so
+// ties between steps are still broken alphabetically. `Graph` keeps one copy of
+// each name, which every use of it shares.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StepName(Rc<str>);
+
+impl StepName {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for StepName {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        lazy_static! {
+            static ref NAME_EXPR: Regex = Regex::new(&format!("^{}$", NAME_PATTERN)).unwrap();
+        }
+
+        if NAME_EXPR.is_match(name) {
+            Ok(StepName(Rc::from(name)))
+        } else {
+            Err(format!("Invalid step name \"{}\"", name))
+        }
+    }
+}
+
+impl Borrow<str> for StepName {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for StepName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Debug for StepName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+// Letters, digits, `_`, `-` and `.`, starting with a letter, digit or `_`.
+const NAME_PATTERN: &str = r"[A-Za-z0-9_][A-Za-z0-9_.\-]*";
+
+// Single-letter names are run together as in the puzzle, e.g. `CABDFE`, and longer
+// names are separated by spaces.
+pub fn format_order(order: &[StepName]) -> String {
+    let separator = if order.iter().all(|step| step.as_str().chars().count() == 1) {
+        ""
+    } else {
+        " "
+    };
+
+    let names: Vec<&str> = order.iter().map(|step| step.as_str()).collect();
+    names.join(separator)
+}
 
 type PriorSteps = HashSet<StepName>;
 
+// Steps that can never be started because each depends on the one before it. Each
+// step must be finished before the next, and the last is the same as the first.
+#[derive(PartialEq, Eq, Debug)]
+pub struct CycleError {
+    steps: Vec<StepName>,
+}
+
+impl CycleError {
+    pub fn steps(&self) -> &[StepName] {
+        &self.steps
+    }
+}
+
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let steps: Vec<String> = self.steps.iter().map(|step| step.to_string()).collect();
+        write!(f, "Dependency cycle: {}", steps.join(" -> "))
+    }
+}
+
 #[derive(PartialEq, Eq, Debug)]
-struct StepParseError();
+pub enum GraphProblem {
+    Cycle(CycleError),
+    SelfDependency(StepName),
+    DuplicateEdge {
+        depends_on: StepName,
+        current_step: StepName,
+    },
+}
+
+impl fmt::Display for GraphProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GraphProblem::Cycle(cycle) => write!(f, "{}", cycle),
+            GraphProblem::SelfDependency(step) => write!(f, "Step {} depends on itself", step),
+            GraphProblem::DuplicateEdge {
+                depends_on,
+                current_step,
+            } => write!(
+                f,
+                "Step {} is listed more than once as depending on step {}",
+                current_step, depends_on
+            ),
+        }
+    }
+}
 
 #[derive(PartialEq, Eq, Debug)]
 struct Edge {
@@ -18,12 +129,12 @@ struct Edge {
 }
 
 impl Edge {
-    pub fn current_step(&self) -> StepName {
-        self.current_step
+    pub fn current_step(&self) -> &StepName {
+        &self.current_step
     }
 
-    pub fn depends_on(&self) -> StepName {
-        self.depends_on
+    pub fn depends_on(&self) -> &StepName {
+        &self.depends_on
     }
 }
 
@@ -32,8 +143,11 @@ impl FromStr for Edge {
 
     fn from_str(input_line: &str) -> Result<Self, Self::Err> {
         lazy_static! {
-            static ref PARSING_EXPR: Regex =
-                Regex::new(r"^Step (?P<depends_on>[A-Z]) must be finished before step (?P<current_step>[A-Z]) can begin.$").unwrap();
+            static ref PARSING_EXPR: Regex = Regex::new(&format!(
+                r"^Step (?P<depends_on>{}) must be finished before step (?P<current_step>{}) can begin.$",
+                NAME_PATTERN, NAME_PATTERN
+            ))
+            .unwrap();
         }
 
         let captures = PARSING_EXPR
@@ -47,166 +161,402 @@ impl FromStr for Edge {
     }
 }
 
-type TimeRemaining = u32;
+// Which steps depend on each step, and how many unfinished prior steps each step
+// is still waiting on, so that finishing a step only touches the steps that
+// depend on it.
+struct Dependencies<'a> {
+    dependents: HashMap<&'a StepName, Vec<&'a StepName>>,
+    unfinished_priors: HashMap<&'a StepName, usize>,
+}
 
-struct StepsInProgress(HashMap<StepName, TimeRemaining>);
+impl<'a> Dependencies<'a> {
+    fn new(graph: &'a Graph, skip_self_dependencies: bool) -> Self {
+        let mut dependencies = Dependencies {
+            dependents: HashMap::with_capacity(graph.steps.len()),
+            unfinished_priors: HashMap::with_capacity(graph.steps.len()),
+        };
+        for (step_name, prior_steps) in graph.steps.iter() {
+            let mut count = 0;
+            for prior in prior_steps.iter() {
+                if !skip_self_dependencies || prior != step_name {
+                    dependencies
+                        .dependents
+                        .entry(prior)
+                        .or_default()
+                        .push(step_name);
+                    count += 1;
+                }
+            }
+            dependencies.unfinished_priors.insert(step_name, count);
+        }
 
-impl StepsInProgress {
-    fn new() -> Self {
-        StepsInProgress(HashMap::with_capacity(5))
+        dependencies
     }
 
-    fn complete_steps(&mut self, time_jump: TimeRemaining) -> Vec<StepName> {
-        for remaining_time in self.0.values_mut() {
-            *remaining_time -= time_jump
-        }
-
-        let completed: Vec<StepName> = self
-            .0
+    // The steps that can be started straight away.
+    fn ready_steps(&self) -> impl Iterator<Item = &'a StepName> + '_ {
+        self.unfinished_priors
             .iter()
-            .filter(|(_, time_remaining)| **time_remaining == 0)
+            .filter(|(_, count)| **count == 0)
             .map(|(step_name, _)| *step_name)
-            .collect();
+    }
 
-        for step in completed.iter() {
-            self.0.remove(step);
+    // Marks a step as finished, passing on any steps that were only waiting on it.
+    fn finish<F>(&mut self, step_name: &StepName, mut on_ready: F)
+    where
+        F: FnMut(&'a StepName),
+    {
+        for dependent in self.dependents.get(step_name).into_iter().flatten() {
+            let count = self.unfinished_priors.get_mut(dependent).unwrap();
+            *count -= 1;
+            if *count == 0 {
+                on_ready(dependent);
+            }
         }
-
-        completed
     }
+}
 
-    fn smallest_time_remaining(&self) -> u32 {
-        *self
-            .0
-            .values()
-            .fold(None, |min, curr| match min {
-                None => Some(curr),
-                Some(existing) => Some(if curr < existing { curr } else { existing }),
-            })
-            .unwrap_or(&0)
+pub struct Graph {
+    steps: HashMap<StepName, PriorSteps>,
+    duplicate_edges: Vec<Edge>,
+}
+
+impl Graph {
+    pub fn new(input: &str) -> Self {
+        let mut graph: Graph = Graph {
+            steps: HashMap::with_capacity(input.len()),
+            duplicate_edges: vec![],
+        };
+        for line in input.lines() {
+            let edge = Edge::from_str(line).unwrap();
+            graph.add_edge(edge.depends_on(), edge.current_step());
+        }
+
+        graph
     }
 
-    fn step_in_progress(&self, step_name: StepName) -> bool {
-        self.0.keys().any(|key| key == &step_name)
+    pub fn empty() -> Self {
+        Graph {
+            steps: HashMap::new(),
+            duplicate_edges: vec![],
+        }
     }
 
-    fn start_step(&mut self, new_step: StepName, new_step_duration: TimeRemaining) {
-        self.0.insert(new_step, new_step_duration);
+    // Adds a step with nothing it depends on, unless it's already in the graph.
+    pub fn add_step(&mut self, name: &StepName) {
+        self.intern(name);
     }
 
-    fn num_available_workers(&self) -> usize {
-        5 - self.0.keys().len()
+    pub fn add_edge(&mut self, depends_on: &StepName, current_step: &StepName) {
+        let depends_on = self.intern(depends_on);
+        let current_step = self.intern(current_step);
+
+        let current = self.steps.entry(current_step.clone()).or_default();
+        if !current.insert(depends_on.clone()) {
+            self.duplicate_edges.push(Edge {
+                current_step,
+                depends_on,
+            });
+        }
     }
-}
 
-pub struct Graph(HashMap<StepName, PriorSteps>);
+    // Every step, alphabetically.
+    pub fn step_names(&self) -> Vec<&StepName> {
+        let mut step_names: Vec<&StepName> = self.steps.keys().collect();
+        step_names.sort_unstable();
+        step_names
+    }
 
-impl Graph {
-    pub fn new(input: &str) -> Self {
-        let mut graph: Graph = Graph(HashMap::with_capacity(input.len()));
-        for line in input.lines() {
-            let edge = Edge::from_str(line).unwrap();
+    // Every dependency as a (depends on, step) pair, sorted.
+    pub fn edges(&self) -> Vec<(&StepName, &StepName)> {
+        let mut edges: Vec<(&StepName, &StepName)> = self
+            .steps
+            .iter()
+            .flat_map(|(step_name, prior_steps)| {
+                prior_steps.iter().map(move |prior| (prior, step_name))
+            })
+            .collect();
+        edges.sort_unstable();
+        edges
+    }
 
-            // make sure we create a root node
-            graph
-                .0
-                .entry(edge.depends_on())
-                .or_insert_with(HashSet::new);
-
-            let current = graph
-                .0
-                .entry(edge.current_step())
-                .or_insert_with(HashSet::new);
-            current.insert(edge.depends_on());
+    // The graph's own copy of a step name, adding the step if it's new.
+    fn intern(&mut self, name: &StepName) -> StepName {
+        if let Some((existing, _)) = self.steps.get_key_value(name.as_str()) {
+            return existing.clone();
         }
 
-        graph
+        self.steps.insert(name.clone(), HashSet::new());
+        name.clone()
     }
 
-    pub fn find_in_order(&self) -> String {
-        let mut completed_steps: Vec<StepName> = vec![];
-        let total_steps = self.0.keys().len();
+    // Checks for everything that would stop the steps being ordered, or suggests a
+    // mistake in the input: self-dependencies, repeated edges, and cycles. Cycles
+    // are found by ordering the steps as `find_in_order` does, and whenever that
+    // gets stuck, recording a cycle and treating its steps as done, so each cycle
+    // reported has steps no other cycle reported shares.
+    pub fn validate(&self) -> Result<(), Vec<GraphProblem>> {
+        let mut problems = vec![];
 
-        while completed_steps.len() < total_steps {
-            let mut completable_steps: Vec<&StepName> = self
-                .0
+        let mut self_dependencies: Vec<StepName> = self
+            .steps
+            .iter()
+            .filter(|(step_name, prior_steps)| prior_steps.contains(*step_name))
+            .map(|(step_name, _)| step_name.clone())
+            .collect();
+        self_dependencies.sort_unstable();
+        problems.extend(
+            self_dependencies
+                .into_iter()
+                .map(GraphProblem::SelfDependency),
+        );
+
+        problems.extend(
+            self.duplicate_edges
                 .iter()
-                .filter(|(step_name, prior_steps)| {
-                    !completed_steps.contains(step_name)
-                        && prior_steps.iter().all(|s| completed_steps.contains(s))
-                })
-                .map(|(step_name, _)| step_name)
-                .collect();
-
-            completable_steps.sort_unstable();
+                .map(|edge| GraphProblem::DuplicateEdge {
+                    depends_on: edge.depends_on().clone(),
+                    current_step: edge.current_step().clone(),
+                }),
+        );
+
+        let mut dependencies = Dependencies::new(self, true);
+        let mut ready_steps: BinaryHeap<Reverse<StepName>> = dependencies
+            .ready_steps()
+            .map(|step_name| Reverse(step_name.clone()))
+            .collect();
+        let mut completed_steps: HashSet<StepName> = HashSet::with_capacity(self.steps.len());
+        while completed_steps.len() < self.steps.len() {
+            match ready_steps.pop() {
+                // Steps in a cycle already treated as done can still become ready.
+                Some(Reverse(step_name)) => {
+                    if completed_steps.insert(step_name.clone()) {
+                        dependencies
+                            .finish(&step_name, |ready| ready_steps.push(Reverse(ready.clone())));
+                    }
+                }
+                None => {
+                    let cycle = self.find_cycle(|step| completed_steps.contains(step), true);
+                    for step_name in cycle.steps()[1..].iter() {
+                        if completed_steps.insert(step_name.clone()) {
+                            dependencies.finish(step_name, |ready| {
+                                ready_steps.push(Reverse(ready.clone()))
+                            });
+                        }
+                    }
+                    problems.push(GraphProblem::Cycle(cycle));
+                }
+            }
+        }
 
-            completed_steps.push(**completable_steps.first().unwrap());
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
         }
+    }
+
+    // Finds a cycle among the steps not yet completed, which must exist when none of
+    // them can be started. Starting from the first such step, this keeps moving to
+    // the first prior step not yet completed until it reaches a step already seen.
+    fn find_cycle<F>(&self, is_completed: F, skip_self_dependencies: bool) -> CycleError
+    where
+        F: Fn(&StepName) -> bool,
+    {
+        let mut remaining: Vec<StepName> = self
+            .steps
+            .keys()
+            .filter(|step_name| !is_completed(step_name))
+            .cloned()
+            .collect();
+        remaining.sort_unstable();
 
-        String::from_iter(completed_steps)
+        let mut path: Vec<StepName> = vec![remaining[0].clone()];
+        loop {
+            let current = &path[path.len() - 1];
+            let next = self.steps[current]
+                .iter()
+                .filter(|prior| !is_completed(prior))
+                .filter(|prior| !skip_self_dependencies || *prior != current)
+                .min()
+                .cloned()
+                .unwrap();
+
+            if let Some(start) = path.iter().position(|step| *step == next) {
+                // Reversed into the order the steps would be done, and rotated to
+                // start from the first step alphabetically.
+                let mut steps: Vec<StepName> = path[start..].to_vec();
+                steps.reverse();
+                let first = (0..steps.len()).min_by_key(|index| &steps[*index]).unwrap();
+                steps.rotate_left(first);
+                steps.push(steps[0].clone());
+                return CycleError { steps };
+            }
+            path.push(next);
+        }
     }
 
-    pub fn find_in_order_with_durations(&self) -> (String, u32) {
-        let mut completed_steps: Vec<StepName> = vec![];
-        let total_steps = self.0.keys().len();
-        let mut in_progress = StepsInProgress::new();
-        let mut elapsed_time = 0;
+    // Kahn's algorithm, always taking the first ready step alphabetically.
+    pub fn find_in_order(&self) -> Result<Vec<StepName>, CycleError> {
+        let mut dependencies = Dependencies::new(self, false);
+        let mut ready_steps: BinaryHeap<Reverse<StepName>> = dependencies
+            .ready_steps()
+            .map(|step_name| Reverse(step_name.clone()))
+            .collect();
+        let mut completed_steps: Vec<StepName> = Vec::with_capacity(self.steps.len());
 
-        while completed_steps.len() < total_steps {
-            let time_jump = in_progress.smallest_time_remaining();
-            elapsed_time += time_jump;
+        while let Some(Reverse(step_name)) = ready_steps.pop() {
+            dependencies.finish(&step_name, |ready| ready_steps.push(Reverse(ready.clone())));
+            completed_steps.push(step_name);
+        }
 
-            let mut just_completed = in_progress.complete_steps(time_jump);
-            just_completed.sort_unstable();
-            completed_steps.append(&mut just_completed.clone());
+        if completed_steps.len() < self.steps.len() {
+            let completed: HashSet<&StepName> = completed_steps.iter().collect();
+            return Err(self.find_cycle(|step| completed.contains(step), false));
+        }
 
-            let mut available_steps: Vec<&StepName> = self
-                .0
-                .iter()
-                .filter(|(step_name, prior_steps)| {
-                    !completed_steps.contains(step_name)
-                        && !in_progress.step_in_progress(**step_name)
-                        && prior_steps.iter().all(|s| completed_steps.contains(s))
-                })
-                .map(|(step_name, _)| step_name)
-                .collect();
+        Ok(completed_steps)
+    }
 
-            available_steps.sort_unstable();
+    // As `find_in_order`, but with the steps shared between workers. Whenever
+    // workers are free, the ready steps the policy puts first go to the
+    // free workers in order, then time jumps to when the next steps finish, with
+    // every step finishing at that time completed together, alphabetically.
+    pub fn find_in_order_with_durations(
+        &self,
+        schedule: &Schedule,
+        policy: &dyn SchedulingPolicy,
+    ) -> Result<Timeline, CycleError> {
+        let priorities = policy.priorities(self, schedule);
+        let priority = |step_name: &StepName| priorities.get(step_name).copied().unwrap_or(0);
+
+        let mut dependencies = Dependencies::new(self, false);
+        let mut ready_steps: BinaryHeap<(u32, Reverse<StepName>)> = dependencies
+            .ready_steps()
+            .map(|step_name| (priority(step_name), Reverse(step_name.clone())))
+            .collect();
+        let mut free_workers: BinaryHeap<Reverse<usize>> =
+            (0..schedule.workers()).map(Reverse).collect();
+        let mut finishing_steps: BinaryHeap<Reverse<(u32, StepName, usize)>> = BinaryHeap::new();
+        let mut completed_steps: Vec<StepName> = Vec::with_capacity(self.steps.len());
+        let mut timeline = Timeline::new(schedule.workers());
+        let mut elapsed_time = 0;
 
-            if available_steps.is_empty() {
-                continue;
+        loop {
+            while !free_workers.is_empty() && !ready_steps.is_empty() {
+                let (_, Reverse(step_name)) = ready_steps.pop().unwrap();
+                let Reverse(worker) = free_workers.pop().unwrap();
+                let duration = schedule.step_duration(&step_name);
+                timeline.record(worker, step_name.clone(), elapsed_time, duration);
+                finishing_steps.push(Reverse((elapsed_time + duration, step_name, worker)));
             }
 
-            for (_, available) in (1..=in_progress.num_available_workers()).zip(available_steps.iter()) {
-                in_progress.start_step(**available, get_step_duration(**available));
+            match finishing_steps.peek() {
+                Some(Reverse((finish_time, _, _))) => elapsed_time = *finish_time,
+                None => break,
+            }
+            while let Some(Reverse((finish_time, _, _))) = finishing_steps.peek() {
+                if *finish_time > elapsed_time {
+                    break;
+                }
+                let Reverse((_, step_name, worker)) = finishing_steps.pop().unwrap();
+                dependencies.finish(&step_name, |ready| {
+                    ready_steps.push((priority(ready), Reverse(ready.clone())))
+                });
+                completed_steps.push(step_name);
+                free_workers.push(Reverse(worker));
             }
         }
 
-        (String::from_iter(completed_steps), elapsed_time)
+        if completed_steps.len() < self.steps.len() {
+            let completed: HashSet<&StepName> = completed_steps.iter().collect();
+            return Err(self.find_cycle(|step| completed.contains(step), false));
+        }
+
+        timeline.finish(completed_steps, elapsed_time);
+        Ok(timeline)
     }
-}
 
-fn get_step_duration(name: StepName) -> TimeRemaining {
-    "ABCDEFGHIJKLMNOPQRSTUVWXYZ"
-        .char_indices()
-        .find(|(_, ch)| ch == &name)
-        .unwrap()
-        .0 as u32
-        + 61
-}
+    // Works out when each step could start with unlimited workers, going forwards
+    // through the steps in order for the earliest starts and then backwards for
+    // the latest. The critical path follows steps with no slack from the start,
+    // taking the first alphabetically when there's a choice.
+    pub fn critical_path(&self, schedule: &Schedule) -> Result<CriticalPath, CycleError> {
+        let order = self.find_in_order()?;
 
-#[cfg(test)]
-mod test_get_step_duration {
-    use super::get_step_duration;
+        let dependents = Dependencies::new(self, false).dependents;
 
-    #[test]
-    fn test() {
-        assert_eq!(get_step_duration('A'), 1 + 60);
-        assert_eq!(get_step_duration('M'), 13 + 60);
-        assert_eq!(get_step_duration('Z'), 26 + 60);
+        let mut earliest_finish: HashMap<&StepName, u32> = HashMap::new();
+        for step_name in order.iter() {
+            let earliest_start = self.steps[step_name]
+                .iter()
+                .map(|prior| earliest_finish[prior])
+                .max()
+                .unwrap_or(0);
+            earliest_finish.insert(
+                step_name,
+                earliest_start + schedule.step_duration(step_name),
+            );
+        }
+        let length = earliest_finish.values().copied().max().unwrap_or(0);
+
+        let mut timings: HashMap<StepName, StepTiming> = HashMap::new();
+        for step_name in order.iter().rev() {
+            let duration = schedule.step_duration(step_name);
+            let latest_finish = dependents
+                .get(step_name)
+                .into_iter()
+                .flatten()
+                .map(|dependent| timings[*dependent].latest_start)
+                .min()
+                .unwrap_or(length);
+            timings.insert(
+                step_name.clone(),
+                StepTiming {
+                    earliest_start: earliest_finish[step_name] - duration,
+                    latest_start: latest_finish - duration,
+                    duration,
+                },
+            );
+        }
+
+        let mut path: Vec<StepName> = vec![];
+        let mut next = order
+            .iter()
+            .filter(|step_name| {
+                let timing = &timings[*step_name];
+                timing.earliest_start == 0 && timing.slack() == 0
+            })
+            .min();
+        while let Some(step_name) = next {
+            path.push(step_name.clone());
+            let finish = timings[step_name].earliest_finish();
+            next = dependents
+                .get(step_name)
+                .into_iter()
+                .flatten()
+                .copied()
+                .filter(|dependent| {
+                    let timing = &timings[*dependent];
+                    timing.earliest_start == finish && timing.slack() == 0
+                })
+                .min();
+        }
+
+        Ok(CriticalPath::new(timings, path))
     }
 }
 
+// The puzzle's example steps, shared by the tests.
+#[cfg(test)]
+pub const EXAMPLE: &str = "Step C must be finished before step A can begin.
+Step C must be finished before step F can begin.
+Step A must be finished before step B can begin.
+Step A must be finished before step D can begin.
+Step B must be finished before step E can begin.
+Step D must be finished before step E can begin.
+Step F must be finished before step E can begin.";
+
 #[cfg(test)]
 mod test_edge_from_str {
     use super::Edge;
@@ -216,27 +566,45 @@ mod test_edge_from_str {
     fn test() {
         assert_eq!(
             Ok(Edge {
-                depends_on: 'A',
-                current_step: 'B'
+                depends_on: "A".parse().unwrap(),
+                current_step: "B".parse().unwrap()
             }),
             Edge::from_str("Step A must be finished before step B can begin.")
         )
     }
 
+    #[test]
+    fn identifiers() {
+        let edge =
+            Edge::from_str("Step compile-core must be finished before step test_2.1 can begin.")
+                .unwrap();
+        assert_eq!(edge.depends_on().as_str(), "compile-core");
+        assert_eq!(edge.current_step().as_str(), "test_2.1");
+    }
+
     #[test]
     fn bad_input() {
-        assert!(Edge::from_str("Step A must be before step B.").is_err())
+        assert!(Edge::from_str("Step A must be before step B.").is_err());
+        assert!(Edge::from_str("Step -A must be finished before step B can begin.").is_err());
+        assert!(Edge::from_str("Step A B must be finished before step C can begin.").is_err());
     }
 }
 
 #[cfg(test)]
 mod test_graph_ordered {
-    use super::Graph;
+    use super::{format_order, Graph, StepName};
+    use crate::policy::Alphabetical;
+    use crate::schedule::Schedule;
+    use std::collections::HashMap;
 
     #[test]
     fn single_node() {
         assert_eq!(
-            Graph::new("Step A must be finished before step B can begin.").find_in_order(),
+            format_order(
+                &Graph::new("Step A must be finished before step B can begin.")
+                    .find_in_order()
+                    .unwrap()
+            ),
             "AB",
         )
     }
@@ -244,7 +612,7 @@ mod test_graph_ordered {
     #[test]
     fn one_branch() {
         assert_eq!(
-            Graph::new("Step A must be finished before step B can begin.\nStep B must be finished before step C can begin.\nStep C must be finished before step D can begin.").find_in_order(),
+            format_order(&Graph::new("Step A must be finished before step B can begin.\nStep B must be finished before step C can begin.\nStep C must be finished before step D can begin.").find_in_order().unwrap()),
             "ABCD",
         )
     }
@@ -252,8 +620,197 @@ mod test_graph_ordered {
     #[test]
     fn two_branches() {
         assert_eq!(
-            Graph::new("Step A must be finished before step B can begin.\nStep B must be finished before step C can begin.\nStep A must be finished before step D can begin.").find_in_order(),
+            format_order(&Graph::new("Step A must be finished before step B can begin.\nStep B must be finished before step C can begin.\nStep A must be finished before step D can begin.").find_in_order().unwrap()),
             "ABCD",
         )
     }
+
+    #[test]
+    fn named_steps() {
+        let graph = Graph::new(
+            "Step fetch must be finished before step compile-core can begin.
+Step fetch must be finished before step compile-cli can begin.
+Step compile-core must be finished before step link can begin.
+Step compile-cli must be finished before step link can begin.",
+        );
+        assert_eq!(
+            format_order(&graph.find_in_order().unwrap()),
+            "fetch compile-cli compile-core link"
+        );
+    }
+
+    #[test]
+    fn names_are_shared() {
+        let graph = Graph::new(
+            "Step build must be finished before step test can begin.
+Step test must be finished before step ship can begin.",
+        );
+        let order = graph.find_in_order().unwrap();
+        let (build, _) = graph.steps.get_key_value("build").unwrap();
+        let ship_priors: Vec<_> = graph.steps["ship"].iter().collect();
+        assert!(std::rc::Rc::ptr_eq(&order[0].0, &build.0));
+        assert!(std::rc::Rc::ptr_eq(&ship_priors[0].0, &order[1].0));
+    }
+
+    #[test]
+    fn large_graph() {
+        // A binary tree of 100,000 steps, each also depending on the step 7 before it.
+        let name = |index: usize| format!("s{:06}", index).parse::<StepName>().unwrap();
+        let mut graph = Graph::empty();
+        graph.add_step(&name(0));
+        for index in 1..100_000 {
+            graph.add_edge(&name((index - 1) / 2), &name(index));
+            if index >= 7 && index - 7 != (index - 1) / 2 {
+                graph.add_edge(&name(index - 7), &name(index));
+            }
+        }
+        assert_eq!(graph.validate(), Ok(()));
+
+        let order = graph.find_in_order().unwrap();
+        assert_eq!(order.len(), 100_000);
+        let positions: HashMap<&StepName, usize> = order
+            .iter()
+            .enumerate()
+            .map(|(position, step)| (step, position))
+            .collect();
+        assert!(graph
+            .edges()
+            .iter()
+            .all(|(depends_on, step)| positions[depends_on] < positions[step]));
+
+        let timeline = graph
+            .find_in_order_with_durations(&Schedule::new(5, 1).unwrap(), &Alphabetical)
+            .unwrap();
+        assert_eq!(timeline.order().len(), 100_000);
+        let lower_bound = graph
+            .critical_path(&Schedule::new(5, 1).unwrap())
+            .unwrap()
+            .makespan_lower_bound(5);
+        assert!(timeline.total_time() >= lower_bound);
+        assert!(timeline.total_time() < lower_bound * 2);
+    }
+}
+
+#[cfg(test)]
+mod test_graph_problems {
+    use super::{Graph, GraphProblem, StepName};
+    use crate::policy::Alphabetical;
+    use crate::schedule::Schedule;
+
+    fn steps(names: &[&str]) -> Vec<StepName> {
+        names.iter().map(|name| name.parse().unwrap()).collect()
+    }
+
+    const CYCLE: &str = "Step A must be finished before step B can begin.
+Step B must be finished before step C can begin.
+Step C must be finished before step D can begin.
+Step D must be finished before step B can begin.";
+
+    #[test]
+    fn cycle_error_names_the_cycle() {
+        let graph = Graph::new(CYCLE);
+        let error = graph.find_in_order().unwrap_err();
+        assert_eq!(error.steps(), &steps(&["B", "C", "D", "B"])[..]);
+        assert_eq!(error.to_string(), "Dependency cycle: B -> C -> D -> B");
+
+        assert_eq!(
+            graph
+                .find_in_order_with_durations(&Schedule::default(), &Alphabetical)
+                .unwrap_err(),
+            error
+        );
+    }
+
+    #[test]
+    fn self_dependency() {
+        let graph = Graph::new("Step A must be finished before step A can begin.");
+        assert_eq!(
+            graph.find_in_order().unwrap_err().steps(),
+            &steps(&["A", "A"])[..]
+        );
+        assert_eq!(
+            graph.validate(),
+            Err(vec![GraphProblem::SelfDependency("A".parse().unwrap())])
+        );
+    }
+
+    #[test]
+    fn validate_reports_every_problem() {
+        let graph = Graph::new(&format!(
+            "{}
+Step A must be finished before step B can begin.
+Step E must be finished before step F can begin.
+Step F must be finished before step E can begin.
+Step G must be finished before step G can begin.",
+            CYCLE
+        ));
+
+        let problems: Vec<String> = graph
+            .validate()
+            .unwrap_err()
+            .iter()
+            .map(|problem| problem.to_string())
+            .collect();
+        assert_eq!(
+            problems,
+            vec![
+                "Step G depends on itself",
+                "Step B is listed more than once as depending on step A",
+                "Dependency cycle: B -> C -> D -> B",
+                "Dependency cycle: E -> F -> E",
+            ]
+        );
+    }
+
+    #[test]
+    fn valid_graph() {
+        let graph = Graph::new("Step A must be finished before step B can begin.");
+        assert_eq!(graph.validate(), Ok(()));
+    }
+}
+
+#[cfg(test)]
+mod test_graph_with_durations {
+    use super::{format_order, Graph, EXAMPLE};
+    use crate::policy::Alphabetical;
+    use crate::schedule::Schedule;
+
+    #[test]
+    fn puzzle_example() {
+        let schedule = Schedule::new(2, 0).unwrap().with_letter_offsets();
+        let timeline = Graph::new(EXAMPLE)
+            .find_in_order_with_durations(&schedule, &Alphabetical)
+            .unwrap();
+        assert_eq!(
+            (format_order(timeline.order()), timeline.total_time()),
+            ("CABFDE".to_owned(), 15)
+        );
+    }
+
+    #[test]
+    fn one_worker_does_steps_in_order() {
+        let schedule = Schedule::new(1, 0).unwrap().with_letter_offsets();
+        let graph = Graph::new(EXAMPLE);
+        let timeline = graph
+            .find_in_order_with_durations(&schedule, &Alphabetical)
+            .unwrap();
+        assert_eq!(timeline.order(), &graph.find_in_order().unwrap()[..]);
+        assert_eq!(timeline.total_time(), 1 + 2 + 3 + 4 + 5 + 6);
+        assert_eq!(timeline.idle_time(0), 0);
+    }
+
+    #[test]
+    fn overridden_durations() {
+        let schedule = Schedule::new(2, 0)
+            .unwrap()
+            .with_letter_offsets()
+            .with_duration("F".parse().unwrap(), 20);
+        let timeline = Graph::new(EXAMPLE)
+            .find_in_order_with_durations(&schedule, &Alphabetical)
+            .unwrap();
+        assert_eq!(
+            (format_order(timeline.order()), timeline.total_time()),
+            ("CABDFE".to_owned(), 3 + 20 + 5)
+        );
+    }
 }
@@ -0,0 +1,178 @@
+use crate::parser::{CycleError, Graph, StepName};
+use crate::schedule::Schedule;
+use std::collections::HashMap;
+
+// Decides which ready steps the free workers start first: those with the highest
+// priority, and alphabetically between steps with the same priority.
+pub trait SchedulingPolicy {
+    fn name(&self) -> String;
+
+    // Each step's priority, with 0 for any step left out.
+    fn priorities(&self, graph: &Graph, schedule: &Schedule) -> HashMap<StepName, u32>;
+}
+
+pub fn get_policy(name: &str) -> Result<Box<dyn SchedulingPolicy>, String> {
+    match name {
+        "alphabetical" => Ok(Box::new(Alphabetical)),
+        "longest-duration" => Ok(Box::new(LongestDuration)),
+        "most-dependents" => Ok(Box::new(MostDependents)),
+        "critical-path" => Ok(Box::new(CriticalPathFirst)),
+        _ => Err(format!(
+            "Unknown policy '{}', expected alphabetical, longest-duration, most-dependents or critical-path",
+            name
+        )),
+    }
+}
+
+pub fn all_policies() -> Vec<Box<dyn SchedulingPolicy>> {
+    vec![
+        Box::new(Alphabetical),
+        Box::new(LongestDuration),
+        Box::new(MostDependents),
+        Box::new(CriticalPathFirst),
+    ]
+}
+
+// Runs every policy on the same graph and workers, giving each policy's name and
+// how long its schedule takes.
+pub fn compare_policies(
+    graph: &Graph,
+    schedule: &Schedule,
+) -> Result<Vec<(String, u32)>, CycleError> {
+    all_policies()
+        .iter()
+        .map(|policy| {
+            let timeline = graph.find_in_order_with_durations(schedule, policy.as_ref())?;
+            Ok((policy.name(), timeline.total_time()))
+        })
+        .collect()
+}
+
+// The puzzle's policy: every step has the same priority.
+pub struct Alphabetical;
+
+impl SchedulingPolicy for Alphabetical {
+    fn name(&self) -> String {
+        "alphabetical".to_owned()
+    }
+
+    fn priorities(&self, _graph: &Graph, _schedule: &Schedule) -> HashMap<StepName, u32> {
+        HashMap::new()
+    }
+}
+
+pub struct LongestDuration;
+
+impl SchedulingPolicy for LongestDuration {
+    fn name(&self) -> String {
+        "longest-duration".to_owned()
+    }
+
+    fn priorities(&self, graph: &Graph, schedule: &Schedule) -> HashMap<StepName, u32> {
+        graph
+            .step_names()
+            .into_iter()
+            .map(|step_name| (step_name.clone(), schedule.step_duration(step_name)))
+            .collect()
+    }
+}
+
+// Prefers the steps that the most other steps are directly waiting on.
+pub struct MostDependents;
+
+impl SchedulingPolicy for MostDependents {
+    fn name(&self) -> String {
+        "most-dependents".to_owned()
+    }
+
+    fn priorities(&self, graph: &Graph, _schedule: &Schedule) -> HashMap<StepName, u32> {
+        let mut priorities = HashMap::new();
+        for (depends_on, _) in graph.edges() {
+            *priorities.entry(depends_on.clone()).or_insert(0) += 1;
+        }
+
+        priorities
+    }
+}
+
+// Prefers the steps with the longest chain of work from their start to the end of
+// the job, which is how long the job would take from there with unlimited workers.
+pub struct CriticalPathFirst;
+
+impl SchedulingPolicy for CriticalPathFirst {
+    fn name(&self) -> String {
+        "critical-path".to_owned()
+    }
+
+    // A graph with a cycle has no critical path, so all its steps get the same
+    // priority, leaving the scheduler to report the cycle.
+    fn priorities(&self, graph: &Graph, schedule: &Schedule) -> HashMap<StepName, u32> {
+        match graph.critical_path(schedule) {
+            Ok(critical_path) => critical_path
+                .timings()
+                .into_iter()
+                .map(|(step_name, timing)| {
+                    (
+                        step_name.clone(),
+                        critical_path.length() - timing.latest_start,
+                    )
+                })
+                .collect(),
+            Err(_) => HashMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_policies {
+    use super::{compare_policies, get_policy};
+    use crate::parser::{format_order, Graph, EXAMPLE};
+    use crate::schedule::Schedule;
+
+    #[test]
+    fn puzzle_example() {
+        let graph = Graph::new(EXAMPLE);
+        let schedule = Schedule::new(2, 0).unwrap().with_letter_offsets();
+        let run = |name: &str| {
+            let timeline = graph
+                .find_in_order_with_durations(&schedule, get_policy(name).unwrap().as_ref())
+                .unwrap();
+            (format_order(timeline.order()), timeline.total_time())
+        };
+
+        assert_eq!(run("alphabetical"), ("CABFDE".to_owned(), 15));
+        assert_eq!(run("longest-duration"), ("CADFBE".to_owned(), 15));
+        assert_eq!(run("most-dependents"), ("CABFDE".to_owned(), 15));
+        assert_eq!(run("critical-path"), ("CADFBE".to_owned(), 15));
+        assert!(get_policy("random").is_err());
+    }
+
+    #[test]
+    fn comparison() {
+        // Alphabetically the long chain Z -> Y is started last, after the short
+        // independent steps, where every other policy starts it first.
+        let mut graph = Graph::new("Step Z must be finished before step Y can begin.");
+        for step in ["A", "B", "C", "D"].iter() {
+            graph.add_step(&step.parse().unwrap());
+        }
+        let schedule = Schedule::new(2, 0).unwrap().with_letter_offsets();
+        assert_eq!(
+            compare_policies(&graph, &schedule).unwrap(),
+            vec![
+                ("alphabetical".to_owned(), 55),
+                ("longest-duration".to_owned(), 51),
+                ("most-dependents".to_owned(), 51),
+                ("critical-path".to_owned(), 51),
+            ]
+        );
+    }
+
+    #[test]
+    fn cycles_are_still_reported() {
+        let graph = Graph::new(
+            "Step A must be finished before step B can begin.
+Step B must be finished before step A can begin.",
+        );
+        assert!(compare_policies(&graph, &Schedule::default()).is_err());
+    }
+}
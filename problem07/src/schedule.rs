@@ -0,0 +1,172 @@
+use crate::parser::StepName;
+use std::collections::HashMap;
+
+// How many workers are available, and how long each step takes them. By default
+// every step takes the base duration. With the puzzle's letter offsets, a step
+// whose name is a single capital letter also takes its position in the alphabet,
+// so with the puzzle's base of 60 seconds, A takes 61 seconds, Z takes 86 and
+// `compile-core` still takes 60.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Schedule {
+    workers: usize,
+    base_duration: u32,
+    letter_offsets: bool,
+    overrides: HashMap<StepName, u32>,
+}
+
+impl Default for Schedule {
+    fn default() -> Schedule {
+        Schedule {
+            workers: 5,
+            base_duration: 60,
+            letter_offsets: false,
+            overrides: HashMap::new(),
+        }
+    }
+}
+
+impl Schedule {
+    pub fn new(workers: usize, base_duration: u32) -> Result<Schedule, String> {
+        if workers == 0 {
+            return Err("At least one worker is needed".to_owned());
+        }
+
+        Ok(Schedule {
+            workers,
+            base_duration,
+            letter_offsets: false,
+            overrides: HashMap::new(),
+        })
+    }
+
+    // Adds the puzzle's extra time for single capital letters, A taking 1 second
+    // longer than the base duration up to Z taking 26 seconds longer.
+    pub fn with_letter_offsets(mut self) -> Schedule {
+        self.letter_offsets = true;
+        self
+    }
+
+    // Sets a step's duration, replacing the default for it.
+    pub fn with_duration(mut self, step: StepName, duration: u32) -> Schedule {
+        self.overrides.insert(step, duration);
+        self
+    }
+
+    // Reads step durations from a file with one `<step> <duration>` pair per line,
+    // with `#` starting a comment.
+    pub fn with_durations_from(mut self, input: &str) -> Result<Schedule, String> {
+        for (index, line) in input.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let fields: Vec<&str> = line.split_whitespace().collect();
+
+            match fields[..] {
+                [] => {}
+                [step, duration] => {
+                    let step = step
+                        .parse::<StepName>()
+                        .map_err(|e| format!("Line {}: {}", index + 1, e))?;
+                    let duration = duration.parse::<u32>().map_err(|e| {
+                        format!("Line {}: invalid duration '{}': {}", index + 1, duration, e)
+                    })?;
+                    self = self.with_duration(step, duration);
+                }
+                _ => {
+                    return Err(format!(
+                        "Line {}: expected '<step> <duration>', found '{}'",
+                        index + 1,
+                        line
+                    ))
+                }
+            }
+        }
+
+        Ok(self)
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    pub fn base_duration(&self) -> u32 {
+        self.base_duration
+    }
+
+    pub fn step_duration(&self, step: &StepName) -> u32 {
+        if let Some(duration) = self.overrides.get(step) {
+            return *duration;
+        }
+
+        let mut chars = step.as_str().chars();
+        match (chars.next(), chars.next()) {
+            (Some(letter), None) if self.letter_offsets && letter.is_ascii_uppercase() => {
+                self.base_duration + u32::from(letter as u8 - b'A') + 1
+            }
+            _ => self.base_duration,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_schedule {
+    use super::Schedule;
+    use crate::parser::StepName;
+
+    fn step(name: &str) -> StepName {
+        name.parse().unwrap()
+    }
+
+    #[test]
+    fn default_durations() {
+        let schedule = Schedule::default();
+        assert_eq!(schedule.workers(), 5);
+        assert_eq!(schedule.step_duration(&step("A")), 60);
+        assert_eq!(schedule.step_duration(&step("Z")), 60);
+    }
+
+    #[test]
+    fn letter_offsets() {
+        let schedule = Schedule::default().with_letter_offsets();
+        assert_eq!(schedule.step_duration(&step("A")), 1 + 60);
+        assert_eq!(schedule.step_duration(&step("M")), 13 + 60);
+        assert_eq!(schedule.step_duration(&step("Z")), 26 + 60);
+    }
+
+    #[test]
+    fn named_steps() {
+        let schedule = Schedule::default()
+            .with_durations_from("compile-core 300\nAB 4")
+            .unwrap();
+        assert_eq!(schedule.step_duration(&step("compile-core")), 300);
+        assert_eq!(schedule.step_duration(&step("AB")), 4);
+        assert_eq!(schedule.step_duration(&step("link")), 60);
+        assert_eq!(schedule.step_duration(&step("a")), 60);
+
+        let schedule = Schedule::default().with_letter_offsets();
+        assert_eq!(schedule.step_duration(&step("A")), 61);
+        assert_eq!(schedule.step_duration(&step("link")), 60);
+    }
+
+    #[test]
+    fn overrides() {
+        let schedule = Schedule::new(2, 0)
+            .unwrap()
+            .with_letter_offsets()
+            .with_duration(step("B"), 7)
+            .with_durations_from("# minutes\nC 100\n\nD 0  # instant\n")
+            .unwrap();
+
+        assert_eq!(schedule.workers(), 2);
+        assert_eq!(schedule.step_duration(&step("A")), 1);
+        assert_eq!(schedule.step_duration(&step("B")), 7);
+        assert_eq!(schedule.step_duration(&step("C")), 100);
+        assert_eq!(schedule.step_duration(&step("D")), 0);
+    }
+
+    #[test]
+    fn invalid_schedules() {
+        assert!(Schedule::new(0, 60).is_err());
+        assert!(Schedule::default().with_durations_from("A").is_err());
+        assert!(Schedule::default().with_durations_from("A-? 3").is_err());
+        assert!(Schedule::default().with_durations_from("A -3").is_err());
+    }
+}
@@ -0,0 +1,288 @@
+use crate::parser::{format_order, StepName};
+use std::collections::HashMap;
+
+const SVG_WIDTH: f64 = 1000.0;
+const SVG_LABEL_WIDTH: f64 = 80.0;
+const SVG_ROW_HEIGHT: u32 = 30;
+
+// One step being worked on, from the second it started until the second it was
+// finished.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Interval {
+    pub step: StepName,
+    pub start: u32,
+    pub end: u32,
+}
+
+// A record of who did what and when while working through the steps: each
+// worker's intervals in the order they were started, the order the steps were
+// completed in and how long everything took.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Timeline {
+    workers: Vec<Vec<Interval>>,
+    order: Vec<StepName>,
+    total_time: u32,
+}
+
+impl Timeline {
+    pub fn new(workers: usize) -> Timeline {
+        Timeline {
+            workers: vec![vec![]; workers],
+            order: vec![],
+            total_time: 0,
+        }
+    }
+
+    pub fn record(&mut self, worker: usize, step: StepName, start: u32, duration: u32) {
+        self.workers[worker].push(Interval {
+            step,
+            start,
+            end: start + duration,
+        });
+    }
+
+    pub fn finish(&mut self, order: Vec<StepName>, total_time: u32) {
+        self.order = order;
+        self.total_time = total_time;
+    }
+
+    pub fn order(&self) -> &[StepName] {
+        &self.order
+    }
+
+    pub fn total_time(&self) -> u32 {
+        self.total_time
+    }
+
+    pub fn worker_intervals(&self, worker: usize) -> &[Interval] {
+        &self.workers[worker]
+    }
+
+    // The periods, as (start, end) pairs, when a worker had nothing to do before
+    // every step was finished.
+    pub fn idle_periods(&self, worker: usize) -> Vec<(u32, u32)> {
+        let mut periods = vec![];
+        let mut free_from = 0;
+        for interval in self.worker_intervals(worker) {
+            if interval.start > free_from {
+                periods.push((free_from, interval.start));
+            }
+            free_from = interval.end;
+        }
+        if self.total_time > free_from {
+            periods.push((free_from, self.total_time));
+        }
+
+        periods
+    }
+
+    pub fn idle_time(&self, worker: usize) -> u32 {
+        self.idle_periods(worker)
+            .iter()
+            .map(|(start, end)| end - start)
+            .sum()
+    }
+
+    // Lays the timeline out as in the puzzle, one row per second:
+    //   Second   Worker 1   Worker 2   Done
+    //      0        C          .
+    //      ...
+    //      3        A          F       C
+    // Each worker's column is wide enough for the longest step name.
+    pub fn to_table(&self) -> String {
+        let finished_at: HashMap<&StepName, u32> = self
+            .workers
+            .iter()
+            .flatten()
+            .map(|interval| (&interval.step, interval.end))
+            .collect();
+
+        let width = self
+            .workers
+            .iter()
+            .flatten()
+            .map(|interval| interval.step.as_str().chars().count())
+            .chain(std::iter::once(
+                format!("Worker {}", self.workers.len()).len(),
+            ))
+            .max()
+            .unwrap_or(0);
+
+        let mut header = "Second".to_owned();
+        for worker in 1..=self.workers.len() {
+            header.push_str(&format!(
+                "   {:<width$}",
+                format!("Worker {}", worker),
+                width = width
+            ));
+        }
+        header.push_str("   Done");
+        let mut lines = vec![header];
+
+        let mut num_done = 0;
+        for second in 0..=self.total_time {
+            while num_done < self.order.len() && finished_at[&self.order[num_done]] <= second {
+                num_done += 1;
+            }
+
+            let mut line = format!("{:>4}  ", second);
+            for intervals in self.workers.iter() {
+                let step = intervals
+                    .iter()
+                    .find(|interval| interval.start <= second && second < interval.end)
+                    .map_or(".", |interval| interval.step.as_str());
+                line.push_str(&format!("   {:^width$}", step, width = width));
+            }
+            line.push_str("   ");
+            line.push_str(&format_order(&self.order[..num_done]));
+            lines.push(line.trim_end().to_owned());
+        }
+
+        lines.join("\n")
+    }
+
+    // Draws the timeline as an SVG Gantt chart, with a row per worker and time
+    // running from left to right. Idle time is left grey.
+    pub fn to_svg(&self) -> String {
+        let scale = (SVG_WIDTH - SVG_LABEL_WIDTH) / f64::from(self.total_time.max(1));
+        let height = SVG_ROW_HEIGHT * self.workers.len() as u32;
+        let mut svg = vec![format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="monospace" font-size="12">"#,
+            SVG_WIDTH, height
+        )];
+
+        for (worker, intervals) in self.workers.iter().enumerate() {
+            let top = SVG_ROW_HEIGHT * worker as u32;
+            let middle = top + SVG_ROW_HEIGHT / 2;
+            svg.push(format!(
+                r#"<text x="4" y="{}" dominant-baseline="middle">Worker {}</text>"#,
+                middle,
+                worker + 1
+            ));
+            svg.push(format!(
+                r##"<rect x="{}" y="{}" width="{}" height="{}" fill="#e0e0e0"/>"##,
+                SVG_LABEL_WIDTH,
+                top + 2,
+                SVG_WIDTH - SVG_LABEL_WIDTH,
+                SVG_ROW_HEIGHT - 4
+            ));
+
+            for interval in intervals.iter() {
+                let x = SVG_LABEL_WIDTH + f64::from(interval.start) * scale;
+                let width = f64::from(interval.end - interval.start) * scale;
+                svg.push(format!(
+                    r#"<rect x="{:.2}" y="{}" width="{:.2}" height="{}" fill="{}" stroke="white"><title>{} ({}-{})</title></rect>"#,
+                    x,
+                    top + 2,
+                    width,
+                    SVG_ROW_HEIGHT - 4,
+                    step_colour(&interval.step),
+                    interval.step,
+                    interval.start,
+                    interval.end
+                ));
+                svg.push(format!(
+                    r#"<text x="{:.2}" y="{}" text-anchor="middle" dominant-baseline="middle">{}</text>"#,
+                    x + width / 2.0,
+                    middle,
+                    interval.step
+                ));
+            }
+        }
+
+        svg.push("</svg>".to_owned());
+        svg.join("\n")
+    }
+}
+
+// Picks a light colour from the step's name, so a step keeps its colour between
+// charts.
+fn step_colour(step: &StepName) -> String {
+    let hash = step.as_str().bytes().fold(0u32, |hash, byte| {
+        hash.wrapping_mul(31).wrapping_add(u32::from(byte))
+    });
+    format!("hsl({}, 70%, 70%)", hash % 360)
+}
+
+#[cfg(test)]
+mod test_timeline {
+    use crate::parser::{Graph, EXAMPLE};
+    use crate::policy::Alphabetical;
+    use crate::schedule::Schedule;
+
+    #[test]
+    fn puzzle_table() {
+        let timeline = Graph::new(EXAMPLE)
+            .find_in_order_with_durations(
+                &Schedule::new(2, 0).unwrap().with_letter_offsets(),
+                &Alphabetical,
+            )
+            .unwrap();
+        assert_eq!(
+            timeline.to_table(),
+            "Second   Worker 1   Worker 2   Done
+   0        C          .
+   1        C          .
+   2        C          .
+   3        A          F       C
+   4        B          F       CA
+   5        B          F       CA
+   6        D          F       CAB
+   7        D          F       CAB
+   8        D          F       CAB
+   9        D          .       CABF
+  10        E          .       CABFD
+  11        E          .       CABFD
+  12        E          .       CABFD
+  13        E          .       CABFD
+  14        E          .       CABFD
+  15        .          .       CABFDE"
+        );
+    }
+
+    #[test]
+    fn named_steps_table() {
+        let timeline = Graph::new(
+            "Step fetch must be finished before step compile-core can begin.
+Step fetch must be finished before step docs can begin.",
+        )
+        .find_in_order_with_durations(&Schedule::new(2, 1).unwrap(), &Alphabetical)
+        .unwrap();
+        assert_eq!(
+            timeline.to_table(),
+            "Second   Worker 1       Worker 2       Done
+   0        fetch            .
+   1     compile-core       docs       fetch
+   2          .              .         fetch compile-core docs"
+        );
+    }
+
+    #[test]
+    fn idle_time() {
+        let timeline = Graph::new(EXAMPLE)
+            .find_in_order_with_durations(
+                &Schedule::new(2, 0).unwrap().with_letter_offsets(),
+                &Alphabetical,
+            )
+            .unwrap();
+        assert_eq!(timeline.idle_periods(0), vec![]);
+        assert_eq!(timeline.idle_periods(1), vec![(0, 3), (9, 15)]);
+        assert_eq!(timeline.idle_time(1), 9);
+        assert_eq!(timeline.worker_intervals(1).len(), 1);
+    }
+
+    #[test]
+    fn svg_has_a_bar_per_step() {
+        let timeline = Graph::new(EXAMPLE)
+            .find_in_order_with_durations(
+                &Schedule::new(2, 0).unwrap().with_letter_offsets(),
+                &Alphabetical,
+            )
+            .unwrap();
+        let svg = timeline.to_svg();
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>"));
+        assert_eq!(svg.matches("<title>").count(), 6);
+        assert!(svg.contains("<title>E (10-15)</title>"));
+    }
+}
@@ -0,0 +1,182 @@
+use crate::tree::Node;
+use std::ops::RangeInclusive;
+
+// The shape of the trees `random_tree` makes. The depth counts levels, so a tree
+// with a depth of 1 is a single node.
+#[derive(Debug, Clone)]
+pub struct TreeShape {
+    pub children: RangeInclusive<usize>,
+    pub max_depth: usize,
+    pub metadata_entries: RangeInclusive<usize>,
+    pub metadata_values: RangeInclusive<usize>,
+}
+
+// Similar to the puzzle input, though smaller.
+impl Default for TreeShape {
+    fn default() -> TreeShape {
+        TreeShape {
+            children: 0..=4,
+            max_depth: 5,
+            metadata_entries: 1..=3,
+            metadata_values: 1..=9,
+        }
+    }
+}
+
+// A small seeded random number generator (SplitMix64), so that a seed always gives
+// the same tree.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn in_range(&mut self, range: &RangeInclusive<usize>) -> usize {
+        match (range.end() - range.start()).checked_add(1) {
+            Some(width) => range.start() + (self.next_u64() % width as u64) as usize,
+            None => self.next_u64() as usize,
+        }
+    }
+}
+
+// A node still having its children made.
+struct PendingNode {
+    depth: usize,
+    children_left: usize,
+    children: Vec<Node>,
+}
+
+// Makes a random tree with the given shape, keeping the nodes still being made on
+// a stack, so any depth of tree can be made.
+pub fn random_tree(seed: u64, shape: &TreeShape) -> Node {
+    let mut rng = Rng::new(seed);
+    let new_node = |rng: &mut Rng, depth: usize| PendingNode {
+        depth,
+        children_left: match depth < shape.max_depth {
+            true => rng.in_range(&shape.children),
+            false => 0,
+        },
+        children: vec![],
+    };
+
+    let mut stack = vec![new_node(&mut rng, 1)];
+    loop {
+        let top = stack.last_mut().unwrap();
+        if top.children_left > 0 {
+            top.children_left -= 1;
+            let depth = top.depth + 1;
+            stack.push(new_node(&mut rng, depth));
+            continue;
+        }
+
+        let pending = stack.pop().unwrap();
+        let metadata = (0..rng.in_range(&shape.metadata_entries))
+            .map(|_| rng.in_range(&shape.metadata_values))
+            .collect();
+        let node = Node {
+            children: pending.children,
+            metadata,
+        };
+
+        match stack.last_mut() {
+            Some(parent) => parent.children.push(node),
+            None => return node,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_round_trip {
+    use super::{random_tree, Rng, TreeShape};
+    use crate::tree::{Node, EXAMPLE};
+
+    fn as_input(data: &[usize]) -> String {
+        let numbers: Vec<String> = data.iter().map(|number| number.to_string()).collect();
+        numbers.join(" ")
+    }
+
+    #[test]
+    fn puzzle_example() {
+        assert_eq!(Node::parse(&EXAMPLE).unwrap().encode(), EXAMPLE.to_vec());
+    }
+
+    #[test]
+    fn random_trees() {
+        let shapes = [
+            TreeShape::default(),
+            TreeShape {
+                children: 0..=12,
+                max_depth: 3,
+                metadata_entries: 0..=5,
+                metadata_values: 0..=20,
+            },
+            TreeShape {
+                children: 1..=2,
+                max_depth: 8,
+                metadata_entries: 0..=0,
+                metadata_values: 0..=0,
+            },
+        ];
+
+        for shape in shapes.iter() {
+            for seed in 0..100 {
+                let tree = random_tree(seed, shape);
+                let data = tree.encode();
+                assert_eq!(Node::parse(&data).unwrap(), tree);
+                assert_eq!(Node::parse(&data).unwrap().encode(), data);
+                assert_eq!(as_input(&data).parse::<Node>().unwrap(), tree);
+                assert!(tree.depth() <= shape.max_depth);
+                assert!(tree
+                    .depth_first()
+                    .all(|node| shape.metadata_entries.contains(&node.metadata.len())));
+            }
+        }
+    }
+
+    #[test]
+    fn deep_random_tree() {
+        let shape = TreeShape {
+            children: 1..=1,
+            max_depth: 200_000,
+            metadata_entries: 1..=2,
+            metadata_values: 1..=2,
+        };
+        let tree = random_tree(7, &shape);
+        assert_eq!(tree.depth(), 200_000);
+
+        let data = tree.encode();
+        assert_eq!(
+            data.len(),
+            2 * 200_000
+                + tree
+                    .depth_first()
+                    .map(|node| node.metadata.len())
+                    .sum::<usize>()
+        );
+        assert_eq!(Node::parse(&data).unwrap().encode(), data);
+    }
+
+    #[test]
+    fn seeded() {
+        let shape = TreeShape::default();
+        assert_eq!(random_tree(1, &shape), random_tree(1, &shape));
+        assert_ne!(
+            random_tree(1, &shape).encode(),
+            random_tree(2, &shape).encode()
+        );
+
+        let mut rng = Rng::new(0);
+        assert!((0..1000).all(|_| (3..=5).contains(&rng.in_range(&(3..=5)))));
+        assert_eq!(rng.in_range(&(4..=4)), 4);
+        rng.in_range(&(0..=usize::MAX));
+    }
+}
@@ -1,142 +1,99 @@
-use std::collections::HashMap;
+use crate::generate::{random_tree, TreeShape};
+use crate::tree::{Node, ParseError};
 use std::fs;
 
+mod generate;
+mod tree;
+
 fn main() -> Result<(), String> {
+    if let Some(seed) = get_arg_value("--generate")? {
+        let seed = seed.parse().map_err(|e| format!("--generate: {}", e))?;
+        let data = random_tree(seed, &TreeShape::default()).encode();
+        let numbers: Vec<String> = data.iter().map(|number| number.to_string()).collect();
+        println!("{}", numbers.join(" "));
+        return Ok(());
+    }
+
     let input = fs::read_to_string("input/data.txt").map_err(|e| e.to_string())?;
-    let as_array = get_as_array(&input);
+    let root: Node = input.parse().map_err(|e: ParseError| e.to_string())?;
 
-    let ChildProcessResult { metadata_sum, .. } = part1(&as_array);
+    let metadata_sum = part1(&root);
     assert_eq!(metadata_sum, 36891);
     println!("Part 1 = {}", metadata_sum);
 
-    let ChildProcessResult { metadata_sum, .. } = part2(&as_array);
-    assert_eq!(metadata_sum, 20083);
-    println!("Part 2 = {}", metadata_sum);
+    let value = part2(&root);
+    assert_eq!(value, 20083);
+    println!("Part 2 = {}", value);
 
-    Ok(())
-}
-
-fn get_as_array(input: &str) -> Vec<usize> {
-    input
-        .split(' ')
-        .map(|item| item.parse::<usize>().unwrap())
-        .collect()
-}
-
-#[derive(Debug, PartialEq, Eq)]
-struct ChildProcessResult {
-    total_length: usize,
-    metadata_sum: usize,
-}
-
-fn part1(data: &[usize]) -> ChildProcessResult {
-    let num_children = data[0];
-    let num_metadata = data[1];
-
-    let mut running_metadata_sum = 0;
-    let mut running_len_total = 2;
-
-    for _ in 0..num_children {
-        let ChildProcessResult {
-            total_length: additional_len,
-            metadata_sum: additional_sum,
-        } = part1(&data[running_len_total..]);
-        running_len_total += additional_len;
-        running_metadata_sum += additional_sum;
+    if std::env::args().any(|arg| arg == "--print") {
+        println!("{}", root);
     }
+    println!(
+        "Tree has {} nodes, {} levels deep",
+        root.size(),
+        root.depth()
+    );
 
-    let this_metadata_sum: usize = data[running_len_total..running_len_total + num_metadata]
-        .iter()
-        .sum();
-
-    ChildProcessResult {
-        total_length: running_len_total + num_metadata,
-        metadata_sum: running_metadata_sum + this_metadata_sum,
-    }
+    Ok(())
 }
 
-fn part2(data: &[usize]) -> ChildProcessResult {
-    let num_children = data[0];
-    let num_metadata = data[1];
-
-    let mut running_len_total = 2;
-
-    if num_children == 0 {
-        return ChildProcessResult {
-            total_length: 2 + num_metadata,
-            metadata_sum: data[running_len_total..running_len_total + num_metadata]
-                .iter()
-                .sum(),
-        };
-    }
-
-    let mut child_results: HashMap<usize, usize> =
-        HashMap::with_capacity(num_children);
-
-    for child_num in 0..num_children {
-        let child_result: ChildProcessResult = part2(&data[running_len_total..]);
-        running_len_total += child_result.total_length;
-        child_results.insert(child_num + 1, child_result.metadata_sum);
+// Reads the value following a command line flag. The only one is `--generate <seed>`,
+// which prints a random tree in the input's format instead of solving the puzzle,
+// while `--print` prints the input's tree.
+fn get_arg_value(flag: &str) -> Result<Option<String>, String> {
+    let mut args = std::env::args().skip_while(|arg| arg != flag);
+    match args.next() {
+        None => Ok(None),
+        Some(_) => args
+            .next()
+            .map(Some)
+            .ok_or_else(|| format!("{} requires a value", flag)),
     }
+}
 
-    let metadata_sum: usize = data[running_len_total..running_len_total + num_metadata]
-        .iter()
-        .fold(0, |acc, metadata_value| {
-            acc + child_results.get(metadata_value).unwrap_or(&0)
-        });
+fn part1(root: &Node) -> usize {
+    root.breadth_first().map(Node::metadata_sum).sum()
+}
 
-    ChildProcessResult {
-        total_length: running_len_total + num_metadata,
-        metadata_sum,
-    }
+fn part2(root: &Node) -> usize {
+    root.value()
 }
 
 #[cfg(test)]
-mod test_process_child {
-    use super::{process_child, ChildProcessResult};
+mod test_part1 {
+    use super::part1;
+    use crate::tree::{Node, EXAMPLE};
+
+    // The metadata sum, and how many numbers the tree takes up.
+    fn sum_and_length(data: &[usize]) -> (usize, usize) {
+        let root = Node::parse(data).unwrap();
+        (part1(&root), root.encode().len())
+    }
 
     #[test]
     fn no_children() {
-        assert_eq!(
-            ChildProcessResult {
-                total_length: 5,
-                metadata_sum: 9 + 5 + 12
-            },
-            process_child(&[0, 3, 9, 5, 12]),
-        )
+        assert_eq!(sum_and_length(&[0, 3, 9, 5, 12]), (9 + 5 + 12, 5));
     }
 
     #[test]
     fn one_child() {
-        assert_eq!(
-            ChildProcessResult {
-                total_length: 7,
-                metadata_sum: 9 + 5 + 12
-            },
-            process_child(&[1, 2, 0, 1, 9, 5, 12]),
-        )
+        assert_eq!(sum_and_length(&[1, 2, 0, 1, 9, 5, 12]), (9 + 5 + 12, 7));
     }
 
     #[test]
     fn sub_children() {
         assert_eq!(
-            ChildProcessResult {
-                total_length: 16,
-                metadata_sum: 1 + 1 + 2 + 10 + 11 + 12 + 2 + 99
-            },
-            process_child(&[2, 3, 0, 3, 10, 11, 12, 1, 1, 0, 1, 99, 2, 1, 1, 2]),
-        )
+            sum_and_length(&EXAMPLE),
+            (1 + 1 + 2 + 10 + 11 + 12 + 2 + 99, 16)
+        );
     }
 
     #[test]
     fn branched_children() {
         assert_eq!(
-            ChildProcessResult {
-                total_length: 16,
-                metadata_sum: 1 + 1 + 6 + 8 + 4 + 0 + 2 + 9
-            },
-            process_child(&[2, 3, 1, 1, 0, 3, 1, 1, 6, 8, 0, 1, 4, 0, 2, 9]),
-            //             [A, A, B, B, C, C, c, c, c, b, D, D, d, a, a, a]
-        )
+            //               [A, A, B, B, C, C, c, c, c, b, D, D, d, a, a, a]
+            sum_and_length(&[2, 3, 1, 1, 0, 3, 1, 1, 6, 8, 0, 1, 4, 0, 2, 9]),
+            (1 + 1 + 6 + 8 + 4 + 2 + 9, 16)
+        );
     }
 }
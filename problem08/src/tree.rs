@@ -0,0 +1,623 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
+// Why the input isn't a single valid tree. Offsets count the numbers in the input
+// from 0.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    InvalidNumber {
+        offset: usize,
+        token: String,
+    },
+    // The input ended at `offset`, partway through the node starting at
+    // `node_offset`.
+    Truncated {
+        offset: usize,
+        node_offset: usize,
+    },
+    // The header at `offset` asks for more numbers than are left in the input,
+    // counting 2 for each child's header and 1 for each metadata entry.
+    OverlongHeader {
+        offset: usize,
+        num_children: usize,
+        num_metadata: usize,
+        remaining: usize,
+    },
+    // The root node ended at `offset`, with `count` numbers still to come.
+    TrailingNumbers {
+        offset: usize,
+        count: usize,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::InvalidNumber { offset, token } => {
+                write!(f, "Number {}: '{}' isn't a number", offset, token)
+            }
+            ParseError::Truncated {
+                offset,
+                node_offset,
+            } => write!(
+                f,
+                "Number {}: the input ends inside the node starting at number {}",
+                offset, node_offset
+            ),
+            ParseError::OverlongHeader {
+                offset,
+                num_children,
+                num_metadata,
+                remaining,
+            } => write!(
+                f,
+                "Number {}: a header with {} children and {} metadata entries doesn't fit in the {} numbers left",
+                offset, num_children, num_metadata, remaining
+            ),
+            ParseError::TrailingNumbers { offset, count } => write!(
+                f,
+                "Number {}: {} numbers follow the end of the root node",
+                offset, count
+            ),
+        }
+    }
+}
+
+// A node of the license tree: its child nodes and its metadata entries, in the
+// order they appear in the input. Cloning, comparing and debug printing work
+// through the tree without recursing, as everything else does, so they work on
+// trees of any depth.
+#[derive(Default)]
+pub struct Node {
+    pub children: Vec<Node>,
+    pub metadata: Vec<usize>,
+}
+
+impl Node {
+    // Builds the tree from the input's numbers, where each node is a header giving
+    // its number of children and metadata entries, followed by its children and
+    // then its metadata.
+    pub fn parse(data: &[usize]) -> Result<Node, ParseError> {
+        parse_nodes(data)
+    }
+
+    // Every node in the tree, each before its children, which are visited in order.
+    pub fn depth_first(&self) -> DepthFirst<'_> {
+        DepthFirst { stack: vec![self] }
+    }
+
+    // Every node in the tree, level by level.
+    pub fn breadth_first(&self) -> BreadthFirst<'_> {
+        BreadthFirst {
+            queue: VecDeque::from(vec![self]),
+        }
+    }
+
+    // How many nodes there are in the tree.
+    pub fn size(&self) -> usize {
+        self.depth_first().count()
+    }
+
+    // How many levels the tree has, so a node with no children has a depth of 1.
+    pub fn depth(&self) -> usize {
+        let mut deepest = 0;
+        let mut stack = vec![(self, 1)];
+        while let Some((node, depth)) = stack.pop() {
+            deepest = deepest.max(depth);
+            stack.extend(node.children.iter().map(|child| (child, depth + 1)));
+        }
+
+        deepest
+    }
+
+    // The tree as the input's numbers, the inverse of `parse`. Each node's
+    // metadata is written once all of its children have been.
+    pub fn encode(&self) -> Vec<usize> {
+        let mut data = vec![];
+        let mut stack = vec![(self, false)];
+        while let Some((node, children_written)) = stack.pop() {
+            if children_written {
+                data.extend_from_slice(&node.metadata);
+            } else {
+                data.push(node.children.len());
+                data.push(node.metadata.len());
+                stack.push((node, true));
+                stack.extend(node.children.iter().rev().map(|child| (child, false)));
+            }
+        }
+
+        data
+    }
+
+    pub fn metadata_sum(&self) -> usize {
+        self.metadata.iter().sum()
+    }
+
+    // The part 2 value: a node without children is worth its metadata sum, and
+    // any other node is worth the sum of the children its metadata entries
+    // refer to, counting from 1, with entries that aren't a child worth nothing.
+    // Nodes are valued after their children, using a stack of the children's
+    // values rather than recursion, so any depth of tree can be valued.
+    pub fn value(&self) -> usize {
+        let mut stack = vec![(self, false)];
+        let mut values: Vec<usize> = vec![];
+        while let Some((node, children_valued)) = stack.pop() {
+            if node.children.is_empty() {
+                values.push(node.metadata_sum());
+            } else if !children_valued {
+                stack.push((node, true));
+                stack.extend(node.children.iter().rev().map(|child| (child, false)));
+            } else {
+                let child_values = values.split_off(values.len() - node.children.len());
+                values.push(
+                    node.metadata
+                        .iter()
+                        .filter_map(|entry| entry.checked_sub(1))
+                        .filter_map(|index| child_values.get(index))
+                        .sum(),
+                );
+            }
+        }
+
+        values[0]
+    }
+}
+
+// Reads the input's numbers, separated by any whitespace.
+impl FromStr for Node {
+    type Err = ParseError;
+
+    fn from_str(input: &str) -> Result<Node, ParseError> {
+        let data = input
+            .split_whitespace()
+            .enumerate()
+            .map(|(offset, token)| {
+                token
+                    .parse::<usize>()
+                    .map_err(|_| ParseError::InvalidNumber {
+                        offset,
+                        token: token.to_owned(),
+                    })
+            })
+            .collect::<Result<Vec<usize>, ParseError>>()?;
+
+        Node::parse(&data)
+    }
+}
+
+// A node whose children are still being read.
+struct PartialNode {
+    offset: usize,
+    children_left: usize,
+    num_metadata: usize,
+    children: Vec<Node>,
+}
+
+impl PartialNode {
+    fn read_header(data: &[usize], position: &mut usize) -> Result<PartialNode, ParseError> {
+        let offset = *position;
+        if data.len() < offset + 2 {
+            return Err(ParseError::Truncated {
+                offset: data.len(),
+                node_offset: offset,
+            });
+        }
+
+        let (num_children, num_metadata) = (data[offset], data[offset + 1]);
+        let remaining = data.len() - offset - 2;
+        if num_children.saturating_mul(2).saturating_add(num_metadata) > remaining {
+            return Err(ParseError::OverlongHeader {
+                offset,
+                num_children,
+                num_metadata,
+                remaining,
+            });
+        }
+
+        *position += 2;
+        Ok(PartialNode {
+            offset,
+            children_left: num_children,
+            num_metadata,
+            children: vec![],
+        })
+    }
+}
+
+// Reads the tree keeping the nodes still being read on a stack, rather than
+// recursing for each child, so any depth of tree can be read.
+fn parse_nodes(data: &[usize]) -> Result<Node, ParseError> {
+    let mut position = 0;
+    let mut stack = vec![PartialNode::read_header(data, &mut position)?];
+    loop {
+        let top = stack.last_mut().unwrap();
+        if top.children_left > 0 {
+            top.children_left -= 1;
+            stack.push(PartialNode::read_header(data, &mut position)?);
+            continue;
+        }
+
+        let partial = stack.pop().unwrap();
+        let metadata = data
+            .get(position..position + partial.num_metadata)
+            .ok_or(ParseError::Truncated {
+                offset: data.len(),
+                node_offset: partial.offset,
+            })?
+            .to_vec();
+        position += partial.num_metadata;
+        let node = Node {
+            children: partial.children,
+            metadata,
+        };
+
+        match stack.last_mut() {
+            Some(parent) => parent.children.push(node),
+            None if position < data.len() => {
+                return Err(ParseError::TrailingNumbers {
+                    offset: position,
+                    count: data.len() - position,
+                })
+            }
+            None => return Ok(node),
+        }
+    }
+}
+
+// Dropping a node would otherwise drop its children recursively, overflowing the
+// stack for deep enough trees, so the descendants are moved onto a list first.
+impl Drop for Node {
+    fn drop(&mut self) {
+        let mut descendants = std::mem::take(&mut self.children);
+        while let Some(mut node) = descendants.pop() {
+            descendants.append(&mut node.children);
+        }
+    }
+}
+
+// Clones the nodes on a stack, each with the clones of its children so far, in
+// the same way `parse_nodes` builds the tree.
+impl Clone for Node {
+    fn clone(&self) -> Node {
+        let mut stack: Vec<(&Node, Vec<Node>)> = vec![(self, vec![])];
+        loop {
+            let (source, children) = stack.last().unwrap();
+            let source: &Node = source;
+            if children.len() < source.children.len() {
+                stack.push((&source.children[children.len()], vec![]));
+                continue;
+            }
+
+            let (source, children) = stack.pop().unwrap();
+            let node = Node {
+                children,
+                metadata: source.metadata.clone(),
+            };
+            match stack.last_mut() {
+                Some((_, siblings)) => siblings.push(node),
+                None => return node,
+            }
+        }
+    }
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Node) -> bool {
+        let mut pairs = vec![(self, other)];
+        while let Some((node, other)) = pairs.pop() {
+            if node.metadata != other.metadata || node.children.len() != other.children.len() {
+                return false;
+            }
+            pairs.extend(node.children.iter().zip(other.children.iter()));
+        }
+
+        true
+    }
+}
+
+impl Eq for Node {}
+
+// Prints the same as a derived `Debug` would without `{:#?}`, e.g.
+//   Node { children: [Node { children: [], metadata: [99] }], metadata: [2] }
+impl fmt::Debug for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        enum Part<'a> {
+            Start(&'a Node),
+            End(&'a Node),
+            Separator,
+        }
+
+        let mut parts = vec![Part::Start(self)];
+        while let Some(part) = parts.pop() {
+            match part {
+                Part::Start(node) => {
+                    write!(f, "Node {{ children: [")?;
+                    parts.push(Part::End(node));
+                    for (index, child) in node.children.iter().enumerate().rev() {
+                        parts.push(Part::Start(child));
+                        if index > 0 {
+                            parts.push(Part::Separator);
+                        }
+                    }
+                }
+                Part::End(node) => write!(f, "], metadata: {:?} }}", node.metadata)?,
+                Part::Separator => write!(f, ", ")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub struct DepthFirst<'a> {
+    stack: Vec<&'a Node>,
+}
+
+impl<'a> Iterator for DepthFirst<'a> {
+    type Item = &'a Node;
+
+    fn next(&mut self) -> Option<&'a Node> {
+        let node = self.stack.pop()?;
+        self.stack.extend(node.children.iter().rev());
+        Some(node)
+    }
+}
+
+pub struct BreadthFirst<'a> {
+    queue: VecDeque<&'a Node>,
+}
+
+impl<'a> Iterator for BreadthFirst<'a> {
+    type Item = &'a Node;
+
+    fn next(&mut self) -> Option<&'a Node> {
+        let node = self.queue.pop_front()?;
+        self.queue.extend(node.children.iter());
+        Some(node)
+    }
+}
+
+// Prints each node's metadata on its own line, indented two spaces per level:
+//   [1, 1, 2]
+//     [10, 11, 12]
+//     [2]
+//       [99]
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut stack = vec![(self, 0)];
+        let mut first = true;
+        while let Some((node, level)) = stack.pop() {
+            if !first {
+                writeln!(f)?;
+            }
+            first = false;
+            write!(f, "{}{:?}", "  ".repeat(level), node.metadata)?;
+            stack.extend(node.children.iter().rev().map(|child| (child, level + 1)));
+        }
+
+        Ok(())
+    }
+}
+
+// The puzzle's example, shared by the tests, with nodes A to D:
+//   A [1, 1, 2]
+//     B [10, 11, 12]
+//     C [2]
+//       D [99]
+#[cfg(test)]
+pub const EXAMPLE: [usize; 16] = [2, 3, 0, 3, 10, 11, 12, 1, 1, 0, 1, 99, 2, 1, 1, 2];
+
+#[cfg(test)]
+mod test_node {
+    use super::{Node, EXAMPLE};
+
+    fn leaf(metadata: &[usize]) -> Node {
+        Node {
+            children: vec![],
+            metadata: metadata.to_vec(),
+        }
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Node::parse(&EXAMPLE).unwrap(),
+            Node {
+                children: vec![
+                    leaf(&[10, 11, 12]),
+                    Node {
+                        children: vec![leaf(&[99])],
+                        metadata: vec![2],
+                    },
+                ],
+                metadata: vec![1, 1, 2],
+            }
+        );
+    }
+
+    #[test]
+    fn traversals() {
+        let root = Node::parse(&EXAMPLE).unwrap();
+        let first_entries = |nodes: Vec<&Node>| -> Vec<usize> {
+            nodes.iter().map(|node| node.metadata[0]).collect()
+        };
+
+        assert_eq!(
+            first_entries(root.depth_first().collect()),
+            vec![1, 10, 2, 99]
+        );
+        assert_eq!(
+            first_entries(root.breadth_first().collect()),
+            vec![1, 10, 2, 99]
+        );
+
+        let root = Node::parse(&[2, 1, 1, 1, 0, 1, 5, 3, 0, 1, 4, 9]).unwrap();
+        assert_eq!(
+            first_entries(root.depth_first().collect()),
+            vec![9, 3, 5, 4]
+        );
+        assert_eq!(
+            first_entries(root.breadth_first().collect()),
+            vec![9, 3, 4, 5]
+        );
+    }
+
+    #[test]
+    fn depth_and_size() {
+        let root = Node::parse(&EXAMPLE).unwrap();
+        assert_eq!(root.size(), 4);
+        assert_eq!(root.depth(), 3);
+        assert_eq!(leaf(&[]).size(), 1);
+        assert_eq!(leaf(&[]).depth(), 1);
+    }
+
+    #[test]
+    fn value() {
+        let root = Node::parse(&EXAMPLE).unwrap();
+        assert_eq!(root.value(), 66);
+        assert_eq!(root.children[0].value(), 33);
+        assert_eq!(root.children[1].value(), 0);
+
+        // A 0 entry refers to no child.
+        assert_eq!(Node::parse(&[1, 2, 0, 1, 7, 0, 1]).unwrap().value(), 7);
+    }
+
+    #[test]
+    fn pretty_print() {
+        assert_eq!(
+            Node::parse(&EXAMPLE).unwrap().to_string(),
+            "[1, 1, 2]\n  [10, 11, 12]\n  [2]\n    [99]"
+        );
+    }
+
+    #[test]
+    fn deep_chain() {
+        // A million nodes, each the only child of the one before, with the
+        // deepest holding 5 and every other node referring to its child.
+        let depth = 1_000_000;
+        let mut data = vec![];
+        for _ in 1..depth {
+            data.extend_from_slice(&[1, 1]);
+        }
+        data.extend_from_slice(&[0, 1, 5]);
+        data.extend(std::iter::repeat_n(1, depth - 1));
+
+        let root = Node::parse(&data).unwrap();
+        assert_eq!(root.size(), depth);
+        assert_eq!(root.depth(), depth);
+        assert_eq!(
+            root.depth_first().map(Node::metadata_sum).sum::<usize>(),
+            5 + (depth - 1)
+        );
+        assert_eq!(root.value(), 5);
+
+        let copy = root.clone();
+        assert!(copy == root);
+        assert_eq!(copy.depth(), depth);
+
+        // The deepest node's metadata entry.
+        data[2 * (depth - 1) + 2] = 6;
+        assert!(Node::parse(&data).unwrap() != root);
+
+        let debug = format!("{:?}", root);
+        assert!(debug.starts_with("Node { children: [Node { children: ["));
+        assert!(debug.ends_with("metadata: [1] }], metadata: [1] }"));
+        assert_eq!(debug.matches("Node {").count(), depth);
+    }
+
+    #[test]
+    fn debug_print() {
+        assert_eq!(
+            format!("{:?}", Node::parse(&[2, 1, 0, 1, 7, 0, 0, 3]).unwrap()),
+            "Node { children: [Node { children: [], metadata: [7] }, Node { children: [], metadata: [] }], metadata: [3] }"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_parse_errors {
+    use super::{Node, ParseError};
+
+    fn parse(input: &str) -> Result<Node, ParseError> {
+        input.parse::<Node>()
+    }
+
+    #[test]
+    fn any_whitespace() {
+        let root = parse("2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2\n").unwrap();
+        assert_eq!(root.value(), 66);
+        assert_eq!(parse(" 0\t1\n\n 5  ").unwrap().metadata, vec![5]);
+    }
+
+    #[test]
+    fn invalid_numbers() {
+        assert_eq!(
+            parse("0 1 x"),
+            Err(ParseError::InvalidNumber {
+                offset: 2,
+                token: "x".to_owned()
+            })
+        );
+        assert!(parse("0 2 1 -1").is_err());
+    }
+
+    #[test]
+    fn truncated() {
+        assert_eq!(
+            parse(""),
+            Err(ParseError::Truncated {
+                offset: 0,
+                node_offset: 0
+            })
+        );
+        // The root's metadata is missing.
+        assert_eq!(
+            parse("1 1 0 1 5"),
+            Err(ParseError::Truncated {
+                offset: 5,
+                node_offset: 0
+            })
+        );
+        // The second child's header is missing.
+        assert_eq!(
+            parse("2 0 0 2 7 8"),
+            Err(ParseError::Truncated {
+                offset: 6,
+                node_offset: 6
+            })
+        );
+        assert_eq!(
+            parse("0 1 5 1 0 1").unwrap_err().to_string(),
+            "Number 3: 3 numbers follow the end of the root node"
+        );
+    }
+
+    #[test]
+    fn overlong_headers() {
+        assert_eq!(
+            parse("1 0 1 1 0"),
+            Err(ParseError::OverlongHeader {
+                offset: 2,
+                num_children: 1,
+                num_metadata: 1,
+                remaining: 1
+            })
+        );
+        assert!(matches!(
+            parse("18446744073709551615 18446744073709551615 1"),
+            Err(ParseError::OverlongHeader { offset: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn trailing_numbers() {
+        assert_eq!(
+            parse("0 1 5 7 8"),
+            Err(ParseError::TrailingNumbers {
+                offset: 3,
+                count: 2
+            })
+        );
+    }
+}